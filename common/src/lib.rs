@@ -0,0 +1,28 @@
+//! Shared plumbing for the day binaries, so each day doesn't have to repeat it.
+
+use anyhow::{Context, Result};
+
+/// Reads the puzzle input from the given path, or `input.txt` if there isn't one.
+/// Days pass in the first command line argument, e.g. `read_input(std::env::args().nth(1))`.
+pub fn read_input(path: Option<String>) -> Result<String> {
+    let path = path.unwrap_or_else(|| "input.txt".to_string());
+    std::fs::read_to_string(&path).with_context(|| format!("Failed to read input from {path}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_input() {
+        let path =
+            std::env::temp_dir().join(format!("common_read_input_{}.txt", std::process::id()));
+        std::fs::write(&path, "provided").unwrap();
+        let input = read_input(Some(path.to_string_lossy().into_owned()));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(input.unwrap(), "provided");
+
+        let error = read_input(Some("missing_input.txt".to_string())).unwrap_err();
+        assert!(error.to_string().contains("missing_input.txt"));
+    }
+}
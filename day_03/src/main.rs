@@ -1,8 +1,9 @@
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
+use common::read_input;
 use regex::Regex;
 
-#[derive(Debug)]
-enum Instruction {
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Instruction {
     Mul(u32, u32),
     Do,
     Dont,
@@ -20,7 +21,7 @@ impl Mul {
 }
 
 fn main() -> Result<()> {
-    let raw_input = std::fs::read_to_string("input.txt")?;
+    let raw_input = read_input(std::env::args().nth(1))?;
 
     let part_1_solution = solve_part_1(&raw_input)?;
     println!("Part 1 solution: {}", part_1_solution);
@@ -32,6 +33,11 @@ fn main() -> Result<()> {
 }
 
 fn solve_part_1(input: &str) -> Result<u32> {
+    to_u32(solve_part_1_u64(input)?)
+}
+
+// Same as part 1, but summed as a u64 so large inputs can't overflow.
+fn solve_part_1_u64(input: &str) -> Result<u64> {
     let regex = Regex::new(r"mul\((\d{1,3}),(\d{1,3})\)").context("Invalid regex")?;
 
     let mut instructions = Vec::new();
@@ -43,12 +49,61 @@ fn solve_part_1(input: &str) -> Result<u32> {
         instructions.push(Mul { a, b });
     }
 
-    let sum = instructions.iter().map(|mul| mul.resolve()).sum();
+    let sum = instructions.iter().map(|mul| mul.resolve() as u64).sum();
 
     Ok(sum)
 }
 
 fn solve_part_2(input: &str) -> Result<u32> {
+    let (sum, _) = solve_part_2_stateful(input, true)?;
+    Ok(sum)
+}
+
+// Same as part 2, but summed as a u64 so large inputs can't overflow.
+pub fn solve_part_2_u64(input: &str) -> Result<u64> {
+    let program = parse_program(input)?;
+    let (sum, _) = run_stateful(&program, true);
+    Ok(sum)
+}
+
+// Narrows a sum back down to a u32, failing rather than wrapping if it's too big.
+fn to_u32(sum: u64) -> Result<u32> {
+    u32::try_from(sum).map_err(|_| anyhow!("Sum {} overflows a u32", sum))
+}
+
+// Same as part 2, but starting from the given enabled state and returning the final one,
+// so that input split into several chunks can be processed one after another.
+fn solve_part_2_stateful(input: &str, start_enabled: bool) -> Result<(u32, bool)> {
+    let program = parse_program(input)?;
+    let (sum, enabled) = run_stateful(&program, start_enabled);
+    Ok((to_u32(sum)?, enabled))
+}
+
+// Solves both parts with a single scan of the input, returning (part 1, part 2).
+pub fn solve_both(input: &str) -> Result<(u32, u32)> {
+    let mut enabled = true;
+    let mut all = 0u64;
+    let mut conditional = 0u64;
+
+    for instruction in parse_program(input)? {
+        match instruction {
+            Instruction::Do => enabled = true,
+            Instruction::Dont => enabled = false,
+            Instruction::Mul(a, b) => {
+                let product = a as u64 * b as u64;
+                all += product;
+                if enabled {
+                    conditional += product;
+                }
+            }
+        }
+    }
+
+    Ok((to_u32(all)?, to_u32(conditional)?))
+}
+
+// Parses the ordered list of mul, do and don't instructions in the input.
+pub fn parse_program(input: &str) -> Result<Vec<Instruction>> {
     let regex =
         Regex::new(r"mul\((\d{1,3}),(\d{1,3})\)|do\(\)|don't\(\)").context("Invalid regex")?;
 
@@ -66,19 +121,30 @@ fn solve_part_2(input: &str) -> Result<u32> {
         }
     }
 
-    let mut enabled = true;
+    Ok(instructions)
+}
+
+// Sums the enabled muls in the program, starting enabled. Fails if the sum overflows a u32.
+pub fn run(program: &[Instruction]) -> Result<u32> {
+    let (sum, _) = run_stateful(program, true);
+    to_u32(sum)
+}
+
+// Sums the enabled muls in the program, returning the final enabled state alongside.
+fn run_stateful(program: &[Instruction], start_enabled: bool) -> (u64, bool) {
+    let mut enabled = start_enabled;
     let mut sum = 0;
 
-    for instruction in instructions {
+    for instruction in program {
         match instruction {
             Instruction::Do => enabled = true,
             Instruction::Dont => enabled = false,
-            Instruction::Mul(a, b) if enabled => sum += a * b,
+            Instruction::Mul(a, b) if enabled => sum += *a as u64 * *b as u64,
             _ => {}
         }
     }
 
-    Ok(sum)
+    (sum, enabled)
 }
 
 #[cfg(test)]
@@ -101,4 +167,63 @@ mod tests {
         let actual = solve_part_2(TEST_INPUT_2).unwrap();
         assert_eq!(actual, 48);
     }
+
+    #[test]
+    fn test_part_2_stateful() {
+        let (first, enabled) = solve_part_2_stateful("mul(2,4)don't()", true).unwrap();
+        assert_eq!(first, 8);
+        assert!(!enabled);
+
+        // The second chunk starts disabled, so only the mul after do() counts
+        let (second, enabled) = solve_part_2_stateful("mul(5,5)do()mul(8,5)", enabled).unwrap();
+        assert_eq!(second, 40);
+        assert!(enabled);
+    }
+
+    #[test]
+    fn test_parse_program() {
+        let program = parse_program(TEST_INPUT_2).unwrap();
+        let expected = vec![
+            Instruction::Mul(2, 4),
+            Instruction::Dont,
+            Instruction::Mul(5, 5),
+            Instruction::Mul(11, 8),
+            Instruction::Do,
+            Instruction::Mul(8, 5),
+        ];
+
+        assert_eq!(program, expected);
+        assert_eq!(run(&program).unwrap(), 48);
+    }
+
+    #[test]
+    fn test_solve_both() {
+        for input in [TEST_INPUT, TEST_INPUT_2] {
+            let expected = (solve_part_1(input).unwrap(), solve_part_2(input).unwrap());
+            assert_eq!(solve_both(input).unwrap(), expected);
+        }
+        assert_eq!(solve_both(TEST_INPUT).unwrap(), (161, 161));
+        assert_eq!(solve_both(TEST_INPUT_2).unwrap(), (161, 48));
+    }
+
+    #[test]
+    fn test_u64_accumulation() {
+        // 5000 * 999 * 999 is larger than u32::MAX
+        let input = "mul(999,999)".repeat(5000);
+        let expected = 5000 * 999 * 999;
+
+        assert_eq!(solve_part_1_u64(&input).unwrap(), expected);
+        assert_eq!(solve_part_2_u64(&input).unwrap(), expected);
+        assert!(solve_part_1(&input).is_err());
+        assert!(solve_part_2(&input).is_err());
+    }
+
+    #[test]
+    fn test_large_mul_product() {
+        // The parser only allows 3 digits, but the instructions can be built directly
+        let program = vec![Instruction::Mul(100_000, 100_000)];
+
+        assert_eq!(run_stateful(&program, true), (10_000_000_000, true));
+        assert!(run(&program).is_err());
+    }
 }
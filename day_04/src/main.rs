@@ -1,159 +1,289 @@
 use std::str::FromStr;
 
 use anyhow::Result;
+use common::read_input;
+use rayon::prelude::*;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("Invalid character: {0}")]
     InvalidCharacter(char),
+    #[error("Rows have different widths")]
+    RaggedGrid,
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
-pub enum Letters {
-    X,
-    M,
-    A,
-    S,
+const XMAS: [char; 4] = ['X', 'M', 'A', 'S'];
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct Coordinate {
+    pub x: usize,
+    pub y: usize,
+}
+
+impl Coordinate {
+    pub fn new(x: usize, y: usize) -> Self {
+        Self { x, y }
+    }
+}
+
+/// The eight directions a word can be read in.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Direction {
+    Right,
+    Left,
+    Down,
+    Up,
+    UpRight,
+    UpLeft,
+    DownRight,
+    DownLeft,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::Right,
+        Direction::Left,
+        Direction::Down,
+        Direction::Up,
+        Direction::UpRight,
+        Direction::UpLeft,
+        Direction::DownRight,
+        Direction::DownLeft,
+    ];
+
+    /// The step taken for each letter in this direction, as (dx, dy).
+    pub fn offset(self) -> (isize, isize) {
+        match self {
+            Direction::Right => (1, 0),
+            Direction::Left => (-1, 0),
+            Direction::Down => (0, 1),
+            Direction::Up => (0, -1),
+            Direction::UpRight => (1, -1),
+            Direction::UpLeft => (-1, -1),
+            Direction::DownRight => (1, 1),
+            Direction::DownLeft => (-1, 1),
+        }
+    }
+}
+
+/// A set of directions to search for words in.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum DirSet {
+    /// Right, left, down and up.
+    Orthogonal,
+    /// The four diagonals.
+    Diagonal,
+    /// All eight directions.
+    All,
+}
+
+impl DirSet {
+    pub fn directions(self) -> &'static [Direction] {
+        match self {
+            DirSet::Orthogonal => &Direction::ALL[..4],
+            DirSet::Diagonal => &Direction::ALL[4..],
+            DirSet::All => &Direction::ALL,
+        }
+    }
 }
 
 #[derive(Debug, PartialEq)]
-pub struct Grid(Vec<Vec<Letters>>);
+pub struct Grid(Vec<Vec<char>>);
 
 impl Grid {
     /// Count the number of XMAS words in the grid.
     /// An XMAS word is a word that starts with an X and is followed by M, A, S in any direction.
     pub fn count_xmas(&self) -> u32 {
-        let mut count = 0;
+        self.count_word_dirs(&XMAS, DirSet::All)
+    }
 
-        for (y, row) in self.0.iter().enumerate() {
-            for (x, letter) in row.iter().enumerate() {
-                // We only care about X's since they represent the start of the word.
-                if letter == &Letters::X {
-                    // Check right
-                    if x + 4 <= row.len() {
-                        if row[x + 1] == Letters::M
-                            && row[x + 2] == Letters::A
-                            && row[x + 3] == Letters::S
-                        {
-                            count += 1;
-                        }
-                    }
-                    // Check Left
-                    if x >= 3 {
-                        if row[x - 1] == Letters::M
-                            && row[x - 2] == Letters::A
-                            && row[x - 3] == Letters::S
-                        {
-                            count += 1;
-                        }
-                    }
-                    // Check Down
-                    if y + 4 <= self.0.len() {
-                        if self.0[y + 1][x] == Letters::M
-                            && self.0[y + 2][x] == Letters::A
-                            && self.0[y + 3][x] == Letters::S
-                        {
-                            count += 1;
-                        }
-                    }
-                    // Check Up
-                    if y >= 3 {
-                        if self.0[y - 1][x] == Letters::M
-                            && self.0[y - 2][x] == Letters::A
-                            && self.0[y - 3][x] == Letters::S
-                        {
-                            count += 1;
-                        }
-                    }
-                    // Check Diagonal Up Right
-                    if x + 4 <= row.len() && y >= 3 {
-                        if self.0[y - 1][x + 1] == Letters::M
-                            && self.0[y - 2][x + 2] == Letters::A
-                            && self.0[y - 3][x + 3] == Letters::S
-                        {
-                            count += 1;
-                        }
-                    }
-                    // Check Diagonal Up Left
-                    if x >= 3 && y >= 3 {
-                        if self.0[y - 1][x - 1] == Letters::M
-                            && self.0[y - 2][x - 2] == Letters::A
-                            && self.0[y - 3][x - 3] == Letters::S
-                        {
-                            count += 1;
-                        }
-                    }
-                    // Check Diagonal Down Right
-                    if x + 4 <= row.len() && y + 4 <= self.0.len() {
-                        if self.0[y + 1][x + 1] == Letters::M
-                            && self.0[y + 2][x + 2] == Letters::A
-                            && self.0[y + 3][x + 3] == Letters::S
-                        {
-                            count += 1;
-                        }
-                    }
-                    // Check Diagonal Down Left
-                    if x >= 3 && y + 4 <= self.0.len() {
-                        if self.0[y + 1][x - 1] == Letters::M
-                            && self.0[y + 2][x - 2] == Letters::A
-                            && self.0[y + 3][x - 3] == Letters::S
-                        {
-                            count += 1;
-                        }
-                    }
+    /// Find every XMAS word in the grid, as the coordinate of its X and the direction it reads in.
+    pub fn find_xmas(&self) -> Vec<(Coordinate, Direction)> {
+        self.find_word(&XMAS)
+    }
+
+    /// Count the number of times a word appears in the grid, reading in any of the eight directions.
+    /// A single letter word is only counted once per matching cell, and words that don't fit in the grid never match.
+    pub fn count_word(&self, word: &[char]) -> u32 {
+        self.find_word(word).len() as u32
+    }
+
+    /// Count the number of times a word appears in the grid, only reading in the given set of directions.
+    pub fn count_word_dirs(&self, word: &[char], dirs: DirSet) -> u32 {
+        (0..self.0.len())
+            .map(|y| self.find_word_in_row(y, word, dirs).len() as u32)
+            .sum()
+    }
+
+    /// Find every placement of a word in the grid, as the coordinate of its first letter and the direction it reads in.
+    pub fn find_word(&self, word: &[char]) -> Vec<(Coordinate, Direction)> {
+        (0..self.0.len())
+            .flat_map(|y| self.find_word_in_row(y, word, DirSet::All))
+            .collect()
+    }
+
+    /// Count the number of XMAS words in the grid, scanning the rows in parallel.
+    /// Gives the same result as `count_xmas`, but is faster on large grids.
+    pub fn count_xmas_par(&self) -> u32 {
+        (0..self.0.len())
+            .into_par_iter()
+            .map(|y| self.find_word_in_row(y, &XMAS, DirSet::All).len() as u32)
+            .sum()
+    }
+
+    /// Find every placement of a word that starts in the given row, reading in the given set of directions.
+    fn find_word_in_row(
+        &self,
+        y: usize,
+        word: &[char],
+        dirs: DirSet,
+    ) -> Vec<(Coordinate, Direction)> {
+        let Some(first) = word.first() else {
+            return Vec::new();
+        };
+        let directions = if word.len() == 1 {
+            &dirs.directions()[..1]
+        } else {
+            dirs.directions()
+        };
+
+        let mut found = Vec::new();
+
+        for (x, letter) in self.0[y].iter().enumerate() {
+            // We only care about the first letter since it represents the start of the word.
+            if letter != first {
+                continue;
+            }
+            for &direction in directions {
+                let (dx, dy) = direction.offset();
+                if self.word_at(x, y, dx, dy, word) {
+                    found.push((Coordinate::new(x, y), direction));
                 }
             }
         }
 
-        count
+        found
+    }
+
+    /// Check whether the word can be read from the given cell, stepping by (dx, dy) for every letter.
+    fn word_at(&self, x: usize, y: usize, dx: isize, dy: isize, word: &[char]) -> bool {
+        word.iter().enumerate().all(|(i, expected)| {
+            let x = x.checked_add_signed(dx * i as isize);
+            let y = y.checked_add_signed(dy * i as isize);
+            match (x, y) {
+                (Some(x), Some(y)) => self.get(x, y) == Some(*expected),
+                _ => false,
+            }
+        })
+    }
+
+    /// Flip the grid over its main diagonal, so rows become columns.
+    /// A grid that is w wide and h tall becomes h wide and w tall.
+    /// Assumes every row is as long as the first, like the puzzle input.
+    pub fn transpose(&self) -> Grid {
+        let width = self.0.first().map_or(0, |row| row.len());
+
+        Grid(
+            (0..width)
+                .map(|x| self.0.iter().map(|row| row[x]).collect())
+                .collect(),
+        )
+    }
+
+    /// Rotate the grid 90 degrees clockwise.
+    /// Like `transpose`, a grid that is w wide and h tall becomes h wide and w tall.
+    pub fn rotate90(&self) -> Grid {
+        let mut rotated = self.transpose();
+        for row in rotated.0.iter_mut() {
+            row.reverse();
+        }
+        rotated
+    }
+
+    /// The width and height of the grid.
+    /// Parsing makes sure every row is the same width, so the first row is enough.
+    pub fn dimensions(&self) -> (usize, usize) {
+        let width = self.0.first().map_or(0, |row| row.len());
+        (width, self.0.len())
+    }
+
+    /// Get the letter at the given cell, if it is inside the grid.
+    fn get(&self, x: usize, y: usize) -> Option<char> {
+        self.0.get(y).and_then(|row| row.get(x)).copied()
     }
 
     /// Count the number of crossed Mas words in the grid
     /// A crossed Mas word is a word that has A in the middle and is crossed diagonally by M and S
     pub fn count_x_mas(&self) -> u32 {
-        let mut count = 0;
+        self.find_x_mas().len() as u32
+    }
+
+    /// Count both the XMAS words and the crossed Mas words, as `(count_xmas, count_x_mas)`.
+    /// Only walks the grid once, checking for words at every X and for crosses at every A.
+    pub fn counts(&self) -> (u32, u32) {
+        let (mut xmas, mut x_mas) = (0, 0);
 
         for (y, row) in self.0.iter().enumerate() {
             for (x, letter) in row.iter().enumerate() {
-                // We only care about A's since they represent the middle of the word.
-                if letter == &Letters::A {
-                    // Crosses cannot be on the edge of the grid
-                    if x == 0 || x == row.len() - 1 || y == 0 || y == self.0.len() - 1 {
-                        continue;
+                match letter {
+                    'X' => {
+                        xmas += DirSet::All
+                            .directions()
+                            .iter()
+                            .filter(|direction| {
+                                let (dx, dy) = direction.offset();
+                                self.word_at(x, y, dx, dy, &XMAS)
+                            })
+                            .count() as u32;
                     }
+                    'A' if self.is_x_mas_centre(x, y) => x_mas += 1,
+                    _ => {}
+                }
+            }
+        }
 
-                    let (nw, ne, sw, se) = (
-                        self.0[y - 1][x - 1],
-                        self.0[y - 1][x + 1],
-                        self.0[y + 1][x - 1],
-                        self.0[y + 1][x + 1],
-                    );
-
-                    // We can immediately skip if any of the diagonals are A's or X's
-                    if nw == Letters::A
-                        || nw == Letters::X
-                        || ne == Letters::A
-                        || ne == Letters::X
-                        || sw == Letters::A
-                        || sw == Letters::X
-                        || se == Letters::A
-                        || se == Letters::X
-                    {
-                        continue;
-                    }
+        (xmas, x_mas)
+    }
 
-                    if nw == se || ne == sw {
-                        continue;
-                    }
+    /// Find the middle A of every crossed Mas word in the grid, in reading order.
+    pub fn find_x_mas(&self) -> Vec<Coordinate> {
+        let mut found = Vec::new();
 
-                    count += 1;
+        for (y, row) in self.0.iter().enumerate() {
+            for (x, letter) in row.iter().enumerate() {
+                // We only care about A's since they represent the middle of the word.
+                if *letter == 'A' && self.is_x_mas_centre(x, y) {
+                    found.push(Coordinate::new(x, y));
                 }
             }
         }
 
-        count
+        found
     }
+
+    /// Check whether the cell is the middle of a crossed Mas word, assuming it holds an A.
+    fn is_x_mas_centre(&self, x: usize, y: usize) -> bool {
+        // Crosses cannot be on the edge of the grid
+        if x == 0 || x == self.0[y].len() - 1 || y == 0 || y == self.0.len() - 1 {
+            return false;
+        }
+
+        let (nw, ne, sw, se) = (
+            self.0[y - 1][x - 1],
+            self.0[y - 1][x + 1],
+            self.0[y + 1][x - 1],
+            self.0[y + 1][x + 1],
+        );
+
+        // Both diagonals have to read MAS, in either direction
+        is_mas_diagonal(nw, se) && is_mas_diagonal(ne, sw)
+    }
+}
+
+/// Check whether the two ends of a diagonal are exactly one M and one S.
+fn is_mas_diagonal(a: char, b: char) -> bool {
+    matches!((a, b), ('M', 'S') | ('S', 'M'))
 }
 
 impl FromStr for Grid {
@@ -165,47 +295,52 @@ impl FromStr for Grid {
         for line in s.lines() {
             let mut row = Vec::new();
             for c in line.chars() {
-                let letter = match c {
-                    'X' => Letters::X,
-                    'M' => Letters::M,
-                    'A' => Letters::A,
-                    'S' => Letters::S,
-                    _ => return Err(Error::InvalidCharacter(c)),
-                };
-                row.push(letter);
+                // Any printable ASCII character is allowed, so boards can contain filler like '.'
+                if !c.is_ascii_graphic() {
+                    return Err(Error::InvalidCharacter(c));
+                }
+                row.push(c);
             }
             grid.push(row);
         }
 
+        // The word searches index across rows, so they all have to line up
+        if grid.windows(2).any(|pair| pair[0].len() != pair[1].len()) {
+            return Err(Error::RaggedGrid);
+        }
+
         Ok(Grid(grid))
     }
 }
 
 fn main() -> Result<()> {
-    let raw_input = std::fs::read_to_string("input.txt")?;
+    let raw_input = read_input(std::env::args().nth(1))?;
     let grid = raw_input.parse::<Grid>()?;
 
-    let part_1 = solve_part_1(&grid);
+    // Both parts come out of the same pass over the grid
+    let (part_1, part_2) = grid.counts();
     println!("Part 1: {}", part_1);
-
-    let part_2 = solve_part_2(&grid);
     println!("Part 2: {}", part_2);
 
     Ok(())
 }
 
-fn solve_part_1(grid: &Grid) -> u32 {
+pub fn solve_part_1(grid: &Grid) -> u32 {
     grid.count_xmas()
 }
 
-fn solve_part_2(grid: &Grid) -> u32 {
+pub fn solve_part_2(grid: &Grid) -> u32 {
     grid.count_x_mas()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::Letters::*;
+
+    const X: char = 'X';
+    const M: char = 'M';
+    const A: char = 'A';
+    const S: char = 'S';
 
     const TEST_INPUT: &str = r#"
 MMMSXXMASM
@@ -258,4 +393,153 @@ MXMXAXMASX
 
         assert_eq!(actual, 9);
     }
+
+    #[test]
+    fn test_count_word() {
+        let grid = build_example_grid();
+
+        assert_eq!(grid.count_word(&[S, A, M, X]), 18);
+        assert_eq!(grid.count_word(&[X, M]), 52);
+        assert_eq!(grid.count_word(&[]), 0);
+        // Longer than the grid in every direction
+        assert_eq!(grid.count_word(&[X; 11]), 0);
+    }
+
+    #[test]
+    fn test_count_word_dirs() {
+        let grid = build_example_grid();
+        let orthogonal = grid.count_word_dirs(&XMAS, DirSet::Orthogonal);
+        let diagonal = grid.count_word_dirs(&XMAS, DirSet::Diagonal);
+
+        assert_eq!(orthogonal, 8);
+        assert_eq!(diagonal, 10);
+        assert_eq!(orthogonal + diagonal, 18);
+        assert_eq!(grid.count_word_dirs(&XMAS, DirSet::All), 18);
+    }
+
+    #[test]
+    fn test_find_xmas() {
+        let grid = build_example_grid();
+        let found = grid.find_xmas();
+
+        assert_eq!(found.len(), 18);
+        assert_eq!(found.len() as u32, grid.count_xmas());
+        assert!(found.iter().all(|(c, _)| grid.get(c.x, c.y) == Some(X)));
+        assert!(found.contains(&(Coordinate::new(5, 0), Direction::Right)));
+    }
+
+    #[test]
+    fn test_counts() {
+        let grid = build_example_grid();
+
+        assert_eq!(grid.counts(), (18, 9));
+        assert_eq!(grid.counts(), (solve_part_1(&grid), solve_part_2(&grid)));
+        assert_eq!("".parse::<Grid>().unwrap().counts(), (0, 0));
+    }
+
+    #[test]
+    fn test_find_x_mas() {
+        let grid = build_example_grid();
+        let found = grid.find_x_mas();
+
+        assert_eq!(found.len(), 9);
+        assert_eq!(found.len() as u32, grid.count_x_mas());
+        assert!(found.iter().all(|c| grid.get(c.x, c.y) == Some('A')));
+        assert_eq!(found[0], Coordinate::new(2, 1));
+    }
+
+    #[test]
+    fn test_parse_grid_with_dots() {
+        let grid = "..X...\n.SAMX.\n.A..A.\nXMAS.S\n.X...."
+            .parse::<Grid>()
+            .unwrap();
+
+        assert_eq!(grid.get(0, 0), Some('.'));
+        assert_eq!(grid.count_xmas(), 4);
+        assert!(matches!(
+            "XM\tAS".parse::<Grid>(),
+            Err(Error::InvalidCharacter('\t'))
+        ));
+    }
+
+    #[test]
+    fn test_dimensions() {
+        let grid = build_example_grid();
+        assert_eq!(grid.dimensions(), (10, 10));
+        assert_eq!(
+            "XMAS\nSAMX\nXMAS".parse::<Grid>().unwrap().dimensions(),
+            (4, 3)
+        );
+        assert_eq!("".parse::<Grid>().unwrap().dimensions(), (0, 0));
+    }
+
+    #[test]
+    fn test_parse_ragged_grid() {
+        assert!(matches!(
+            "XMAS\nXM\nXMAS".parse::<Grid>(),
+            Err(Error::RaggedGrid)
+        ));
+        assert!(matches!(
+            "XMAS\nXMASX".parse::<Grid>(),
+            Err(Error::RaggedGrid)
+        ));
+    }
+
+    #[test]
+    fn test_count_x_mas_corners() {
+        // One diagonal is all M's, so this is not a cross
+        let grid = "M.M\n.A.\nS.M".parse::<Grid>().unwrap();
+        assert_eq!(grid.count_x_mas(), 0);
+
+        // Both diagonals are M's, with S's on the opposite corners
+        let grid = "M.S\n.A.\nM.S".parse::<Grid>().unwrap();
+        assert_eq!(grid.count_x_mas(), 1);
+
+        // Both diagonals read MAS backwards
+        let grid = "S.S\n.A.\nM.M".parse::<Grid>().unwrap();
+        assert_eq!(grid.count_x_mas(), 1);
+    }
+
+    #[test]
+    fn test_transpose() {
+        let grid = "XMAS\nSAMX".parse::<Grid>().unwrap();
+        let transposed = grid.transpose();
+
+        assert_eq!(transposed, "XS\nMA\nAM\nSX".parse::<Grid>().unwrap());
+        assert_eq!(transposed.transpose(), grid);
+        assert_eq!(build_example_grid().transpose().count_xmas(), 18);
+    }
+
+    #[test]
+    fn test_rotate90() {
+        let grid = "XMAS\nSAMX".parse::<Grid>().unwrap();
+
+        assert_eq!(grid.rotate90(), "SX\nAM\nMA\nXS".parse::<Grid>().unwrap());
+
+        let example = build_example_grid();
+        let rotated = example.rotate90().rotate90().rotate90().rotate90();
+        assert_eq!(rotated, example);
+        assert_eq!(example.rotate90().count_xmas(), 18);
+    }
+
+    #[test]
+    fn test_count_xmas_par() {
+        // Tile a pseudo-random board large enough to be worth splitting across threads
+        let mut seed = 42u32;
+        let input = (0..200)
+            .map(|_| {
+                (0..200)
+                    .map(|_| {
+                        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+                        XMAS[(seed >> 16) as usize % 4]
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let grid = input.parse::<Grid>().unwrap();
+
+        assert_eq!(grid.count_xmas_par(), grid.count_xmas());
+        assert_eq!(build_example_grid().count_xmas_par(), 18);
+    }
 }
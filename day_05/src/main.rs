@@ -4,9 +4,13 @@ use std::{
 };
 
 use anyhow::Result;
+use common::read_input;
 use thiserror::Error;
 
-type Page = u32;
+pub type Page = u32;
+
+/// Most orderings `all_valid_orderings` will return, unconstrained updates have factorially many.
+const MAX_ORDERINGS: usize = 1000;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct OrderingRule {
@@ -21,9 +25,13 @@ impl OrderingRule {
 }
 
 #[derive(Debug, Clone, PartialEq)]
-struct Update(Vec<Page>);
+pub struct Update(Vec<Page>);
 
 impl Update {
+    pub fn new(pages: Vec<Page>) -> Self {
+        Self(pages)
+    }
+
     fn is_valid(&self, rules: &[OrderingRule]) -> bool {
         // Create a map of pages to their index in the update.
         let map = self.0.iter().enumerate().fold(
@@ -45,6 +53,17 @@ impl Update {
         true
     }
 
+    // Filters the rules to only those where both pages appear in the update.
+    fn relevant_rules(&self, rules: &[OrderingRule]) -> Vec<OrderingRule> {
+        let pages: HashSet<Page> = self.0.iter().copied().collect();
+
+        rules
+            .iter()
+            .filter(|rule| pages.contains(&rule.value) && pages.contains(&rule.before))
+            .copied()
+            .collect()
+    }
+
     // Don't use this lol, it took over 15 minutes to run on the input.
     // I'm keeping it here for posterity.
     fn _correct_update_brute_force(&self, rules: &[OrderingRule]) -> Update {
@@ -57,11 +76,7 @@ impl Update {
         );
 
         // Filter rules to only those which are relevant to the update.
-        let relevant_rules = rules
-            .iter()
-            .filter(|rule| map.contains_key(&rule.value) && map.contains_key(&rule.before))
-            .copied()
-            .collect::<Vec<OrderingRule>>();
+        let relevant_rules = self.relevant_rules(rules);
 
         // Somehow I need to take the Update and apply all the rules to it, so that it is sorted correctly.
         // An issue is that a single run through the rules may not be enough to sort the update correctly.
@@ -86,9 +101,12 @@ impl Update {
         update
     }
 
-    // Mr CoPilot pointed me in the right direction with this one.
-    // I've added some comments for my own understanding.
-    fn correct_update(&self, rules: &[OrderingRule]) -> Update {
+    // Builds a graph from each page to the pages that have to come after it, along with how many
+    // pages have to come before each one. Only rules which are relevant to the update are used.
+    fn rule_graph(
+        &self,
+        rules: &[OrderingRule],
+    ) -> (HashMap<Page, HashSet<Page>>, HashMap<Page, usize>) {
         let mut graph: HashMap<Page, HashSet<Page>> = HashMap::new();
         let mut in_degree: HashMap<Page, usize> = HashMap::new();
 
@@ -99,12 +117,8 @@ impl Update {
         }
 
         // Build the graph and in-degree map based on the rules
-        for rule in rules {
-            // Skip rules which are not relevant to the update. Doesn't work without this check.
-            if !graph.contains_key(&rule.value) || !graph.contains_key(&rule.before) {
-                continue;
-            }
-
+        // Doesn't work without the relevance filter.
+        for rule in self.relevant_rules(rules) {
             if let Some(neighbors) = graph.get_mut(&rule.value) {
                 if neighbors.insert(rule.before) {
                     *in_degree.entry(rule.before).or_insert(0) += 1;
@@ -112,6 +126,14 @@ impl Update {
             }
         }
 
+        (graph, in_degree)
+    }
+
+    // Mr CoPilot pointed me in the right direction with this one.
+    // I've added some comments for my own understanding.
+    fn correct_update(&self, rules: &[OrderingRule]) -> Result<Update, PrintQueueError> {
+        let (graph, mut in_degree) = self.rule_graph(rules);
+
         // Perform topological sort
         let mut queue: VecDeque<Page> = VecDeque::new();
         for (&page, &degree) in &in_degree {
@@ -136,25 +158,141 @@ impl Update {
 
         // If the sorted_pages length is not equal to the original update length, it means there was a cycle
         if sorted_pages.len() != self.0.len() {
-            panic!("Cycle detected in the rules");
+            return Err(PrintQueueError::CycleDetected);
         }
 
-        Update(sorted_pages)
+        Ok(Update(sorted_pages))
     }
 
-    fn get_middle_page(&self) -> Page {
-        // Assuming the update is valid. I.e. has an odd number of pages.
-        self.0[self.0.len() / 2]
+    // Every order of the pages that satisfies the relevant rules, found by backtracking over the same graph
+    // as `correct_update`. Pages with no remaining prerequisites are tried in ascending order, so the result
+    // is the same on every run. Stops after MAX_ORDERINGS, and is empty if the rules contain a cycle.
+    pub fn all_valid_orderings(&self, rules: &[OrderingRule]) -> Vec<Update> {
+        let (graph, mut in_degree) = self.rule_graph(rules);
+
+        fn backtrack(
+            graph: &HashMap<Page, HashSet<Page>>,
+            in_degree: &mut HashMap<Page, usize>,
+            current: &mut Vec<Page>,
+            orderings: &mut Vec<Update>,
+        ) {
+            if orderings.len() >= MAX_ORDERINGS {
+                return;
+            }
+            if current.len() == in_degree.len() {
+                orderings.push(Update(current.clone()));
+                return;
+            }
+
+            let mut ready = in_degree
+                .iter()
+                .filter(|(page, &degree)| degree == 0 && !current.contains(page))
+                .map(|(&page, _)| page)
+                .collect::<Vec<Page>>();
+            ready.sort_unstable();
+
+            for page in ready {
+                // Take the page, freeing up anything that only had to wait for it
+                current.push(page);
+                for neighbor in &graph[&page] {
+                    *in_degree.get_mut(neighbor).unwrap() -= 1;
+                }
+
+                backtrack(graph, in_degree, current, orderings);
+
+                // Put it back for the next choice
+                for neighbor in &graph[&page] {
+                    *in_degree.get_mut(neighbor).unwrap() += 1;
+                }
+                current.pop();
+            }
+        }
+
+        let mut orderings = Vec::new();
+        backtrack(&graph, &mut in_degree, &mut Vec::new(), &mut orderings);
+        orderings
     }
+
+    // Simpler alternative to `correct_update`, relying on the rules giving a total order over the pages present.
+    // Each page is ranked by how many of the other pages have to come before it, which only gives every page
+    // a different rank when the rules fully order the update. Anything else is rejected rather than guessed.
+    pub fn sort_by_rules(&self, rules: &[OrderingRule]) -> Result<Update, PrintQueueError> {
+        let before: HashSet<(Page, Page)> = self
+            .relevant_rules(rules)
+            .iter()
+            .map(|rule| (rule.value, rule.before))
+            .collect();
+        let rank = |page: Page| before.iter().filter(|&&(_, after)| after == page).count();
+
+        let mut pages = self.0.clone();
+        pages.sort_by_key(|&page| rank(page));
+
+        // A total order ranks the pages 0, 1, 2, ...
+        if pages.iter().enumerate().any(|(i, &page)| rank(page) != i) {
+            return Err(PrintQueueError::NotTotallyOrdered);
+        }
+
+        Ok(Update(pages))
+    }
+
+    fn get_middle_page(&self, policy: MiddlePolicy) -> Page {
+        let index = match policy {
+            MiddlePolicy::RequireOdd => {
+                // Assuming the update is valid. I.e. has an odd number of pages.
+                debug_assert!(self.0.len() % 2 == 1, "Update has an even number of pages");
+                self.0.len() / 2
+            }
+            MiddlePolicy::LowerMiddle => self.0.len().saturating_sub(1) / 2,
+            MiddlePolicy::UpperMiddle => self.0.len() / 2,
+        };
+        self.0[index]
+    }
+}
+
+/// Decides which page is the middle one, and whether updates with an even number of pages are allowed.
+/// All policies agree on updates with an odd number of pages.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum MiddlePolicy {
+    /// Updates must have an odd number of pages, even ones are rejected when parsing.
+    #[default]
+    RequireOdd,
+    /// The first of the two middle pages of an even update.
+    LowerMiddle,
+    /// The second of the two middle pages of an even update.
+    UpperMiddle,
 }
 
 #[derive(Debug, PartialEq)]
-struct PrintQueue {
+pub struct PrintQueue {
     rules: Vec<OrderingRule>,
     updates: Vec<Update>,
+    policy: MiddlePolicy,
 }
 
 impl PrintQueue {
+    /// Builds a queue directly, using the same default middle page policy as parsing.
+    /// Like parsing, updates without a middle page under that policy are rejected.
+    pub fn new(rules: Vec<OrderingRule>, updates: Vec<Update>) -> Result<Self, PrintQueueError> {
+        let policy = MiddlePolicy::default();
+        for update in &updates {
+            validate_update(&update.0, policy)?;
+        }
+
+        Ok(Self {
+            rules,
+            updates,
+            policy,
+        })
+    }
+
+    pub fn rules(&self) -> &[OrderingRule] {
+        &self.rules
+    }
+
+    pub fn updates(&self) -> &[Update] {
+        &self.updates
+    }
+
     fn get_valid_updates(&self) -> Vec<&Update> {
         self.updates
             .iter()
@@ -168,10 +306,46 @@ impl PrintQueue {
             .filter(|update| !update.is_valid(&self.rules))
             .collect()
     }
+
+    // Sums the middle pages of the valid updates and of the corrected invalid updates, as (part 1, part 2).
+    // Only checks each update's validity once, instead of once per part.
+    pub fn middle_sums(&self) -> Result<(u32, u32), PrintQueueError> {
+        let (valid, invalid): (Vec<&Update>, Vec<&Update>) = self
+            .updates
+            .iter()
+            .partition(|update| update.is_valid(&self.rules));
+
+        let valid_sum = valid
+            .iter()
+            .map(|update| update.get_middle_page(self.policy))
+            .sum();
+        let corrected_sum = invalid
+            .iter()
+            .map(|update| update.correct_update(&self.rules))
+            .map(|update| update.map(|update| update.get_middle_page(self.policy)))
+            .sum::<Result<u32, _>>()?;
+
+        Ok((valid_sum, corrected_sum))
+    }
+
+    // Checks that the rules are acyclic across every page that appears in any update.
+    // If this fails the rules are contradictory, rather than a particular update being out of order.
+    pub fn has_consistent_ordering(&self) -> bool {
+        let mut pages = self
+            .updates
+            .iter()
+            .flat_map(|update| update.0.iter().copied())
+            .collect::<Vec<Page>>();
+        pages.sort_unstable();
+        pages.dedup();
+
+        // Sorting every page at once only succeeds if the global precedence graph has no cycles.
+        Update(pages).correct_update(&self.rules).is_ok()
+    }
 }
 
 #[derive(Debug, Error)]
-enum PrintQueueError {
+pub enum PrintQueueError {
     #[error("Invalid page number: {0}")]
     CannotParseInt(#[from] std::num::ParseIntError),
     #[error("Invalid queue")]
@@ -180,13 +354,38 @@ enum PrintQueueError {
     MalformedRule(String),
     #[error("Invalid update: {0}")]
     MalformedUpdate(String),
+    #[error("Cycle detected in the rules")]
+    CycleDetected,
+    #[error("Rules don't fully order the update")]
+    NotTotallyOrdered,
+}
+
+impl FromStr for OrderingRule {
+    type Err = PrintQueueError;
+
+    /// Parses a single rule, e.g. "47|53" means page 47 has to come before page 53.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (value_str, before_str) = s
+            .trim()
+            .split_once('|')
+            .ok_or(PrintQueueError::MalformedRule(s.to_string()))?;
+        let value = value_str.parse()?;
+        let before = before_str.parse()?;
+        Ok(OrderingRule { value, before })
+    }
 }
 
 impl FromStr for PrintQueue {
     type Err = PrintQueueError;
 
-    // Works well enough. A possible optimization would be to only include rules which are included in an update.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PrintQueue::parse_with_policy(s, MiddlePolicy::default())
+    }
+}
+
+impl PrintQueue {
+    // Works well enough. A possible optimization would be to only include rules which are included in an update.
+    fn parse_with_policy(s: &str, policy: MiddlePolicy) -> Result<Self, PrintQueueError> {
         let mut rules = Vec::new();
         let mut updates = Vec::new();
 
@@ -198,58 +397,66 @@ impl FromStr for PrintQueue {
             .ok_or(PrintQueueError::MalformedQueue)?;
 
         for line in rules_str.trim().lines() {
-            let (value_str, before_str) = line
-                .split_once('|')
-                .ok_or(PrintQueueError::MalformedRule(line.to_string()))?;
-            let value = value_str.parse()?;
-            let before = before_str.parse()?;
-            rules.push(OrderingRule { value, before });
+            rules.push(line.parse()?);
         }
 
         for line in updates_str.trim().lines() {
+            // There's no middle page in an empty update
+            if line.trim().is_empty() {
+                return Err(PrintQueueError::MalformedUpdate(line.to_string()));
+            }
             let update = line
                 .split(',')
                 .map(|s| s.parse())
                 .collect::<Result<Vec<Page>, _>>()?;
-            if update.len() % 2 == 0 {
-                return Err(PrintQueueError::MalformedUpdate(line.to_string()));
-            }
+            validate_update(&update, policy)?;
             updates.push(Update(update));
         }
 
-        Ok(PrintQueue { rules, updates })
+        Ok(PrintQueue {
+            rules,
+            updates,
+            policy,
+        })
+    }
+}
+
+/// Rejects updates that don't have a middle page under the given policy.
+fn validate_update(pages: &[Page], policy: MiddlePolicy) -> Result<(), PrintQueueError> {
+    if pages.is_empty() || (policy == MiddlePolicy::RequireOdd && pages.len().is_multiple_of(2)) {
+        let update = pages.iter().map(Page::to_string).collect::<Vec<_>>();
+        return Err(PrintQueueError::MalformedUpdate(update.join(",")));
     }
+
+    Ok(())
 }
 
 fn main() -> Result<()> {
-    let raw_input = std::fs::read_to_string("input.txt")?;
+    let raw_input = read_input(std::env::args().nth(1))?;
     let print_queue: PrintQueue = raw_input.parse()?;
 
-    // Part 1
-    let part_1 = solve_part_1(&print_queue);
+    // Both parts in a single pass over the updates
+    let (part_1, part_2) = print_queue.middle_sums()?;
     println!("Part 1: {}", part_1);
-
-    // Part 2
-    let part_2 = solve_part_2(&print_queue);
     println!("Part 2: {}", part_2);
 
     Ok(())
 }
 
-fn solve_part_1(print_queue: &PrintQueue) -> u32 {
+pub fn solve_part_1(print_queue: &PrintQueue) -> u32 {
     let valid_updates = print_queue.get_valid_updates();
     valid_updates
         .iter()
-        .map(|update| update.get_middle_page())
+        .map(|update| update.get_middle_page(print_queue.policy))
         .sum()
 }
 
-fn solve_part_2(print_queue: &PrintQueue) -> u32 {
+pub fn solve_part_2(print_queue: &PrintQueue) -> Result<u32, PrintQueueError> {
     let invalid_updates = print_queue.get_invalid_updates();
     invalid_updates
         .iter()
         .map(|update| update.correct_update(&print_queue.rules))
-        .map(|update| update.get_middle_page())
+        .map(|update| update.map(|update| update.get_middle_page(print_queue.policy)))
         .sum()
 }
 
@@ -289,8 +496,8 @@ mod tests {
 "#;
 
     fn create_test_print_queue() -> PrintQueue {
-        PrintQueue {
-            rules: vec![
+        PrintQueue::new(
+            vec![
                 OrderingRule::new(47, 53),
                 OrderingRule::new(97, 13),
                 OrderingRule::new(97, 61),
@@ -313,15 +520,16 @@ mod tests {
                 OrderingRule::new(75, 13),
                 OrderingRule::new(53, 13),
             ],
-            updates: vec![
-                Update(vec![75, 47, 61, 53, 29]),
-                Update(vec![97, 61, 53, 29, 13]),
-                Update(vec![75, 29, 13]),
-                Update(vec![75, 97, 47, 61, 53]),
-                Update(vec![61, 13, 29]),
-                Update(vec![97, 13, 75, 29, 47]),
+            vec![
+                Update::new(vec![75, 47, 61, 53, 29]),
+                Update::new(vec![97, 61, 53, 29, 13]),
+                Update::new(vec![75, 29, 13]),
+                Update::new(vec![75, 97, 47, 61, 53]),
+                Update::new(vec![61, 13, 29]),
+                Update::new(vec![97, 13, 75, 29, 47]),
             ],
-        }
+        )
+        .unwrap()
     }
 
     #[test]
@@ -332,6 +540,77 @@ mod tests {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn test_build_print_queue() {
+        let print_queue = PrintQueue::new(
+            vec![OrderingRule::new(1, 2), OrderingRule::new(2, 3)],
+            vec![Update::new(vec![1, 2, 3]), Update::new(vec![3, 1, 2])],
+        )
+        .unwrap();
+
+        assert_eq!(print_queue.rules().len(), 2);
+        assert_eq!(print_queue.updates()[1], Update::new(vec![3, 1, 2]));
+        // The first update is already in order, the second one sorts to 1,2,3
+        assert_eq!(solve_part_1(&print_queue), 2);
+        assert_eq!(solve_part_2(&print_queue).unwrap(), 2);
+        assert_eq!(print_queue.middle_sums().unwrap(), (2, 2));
+
+        // Same as parsing, there's no middle page without an odd number of pages
+        for pages in [vec![1, 2], vec![]] {
+            assert!(matches!(
+                PrintQueue::new(vec![], vec![Update::new(pages)]),
+                Err(PrintQueueError::MalformedUpdate(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_parse_ordering_rule() {
+        assert_eq!(
+            "47|53".parse::<OrderingRule>().unwrap(),
+            OrderingRule::new(47, 53)
+        );
+        assert!(matches!(
+            "4753".parse::<OrderingRule>(),
+            Err(PrintQueueError::MalformedRule(line)) if line == "4753"
+        ));
+        assert!(matches!(
+            "47|x".parse::<OrderingRule>(),
+            Err(PrintQueueError::CannotParseInt(_))
+        ));
+    }
+
+    #[test]
+    fn test_all_valid_orderings() {
+        let rules = create_test_print_queue().rules;
+
+        // The sample rules fully order these pages
+        let update = Update::new(vec![75, 29, 13]);
+        assert_eq!(update.all_valid_orderings(&rules), vec![update.clone()]);
+
+        // 3 isn't mentioned by any rule, so it can go anywhere
+        let rules = vec![OrderingRule::new(1, 2)];
+        let orderings = Update::new(vec![3, 2, 1]).all_valid_orderings(&rules);
+        assert_eq!(
+            orderings,
+            vec![
+                Update::new(vec![1, 2, 3]),
+                Update::new(vec![1, 3, 2]),
+                Update::new(vec![3, 1, 2]),
+            ]
+        );
+
+        // Contradictory rules have no valid ordering
+        let cycle = vec![OrderingRule::new(1, 2), OrderingRule::new(2, 1)];
+        assert!(Update::new(vec![1, 2])
+            .all_valid_orderings(&cycle)
+            .is_empty());
+
+        // Seven unconstrained pages have 5040 orderings, more than the cap
+        let update = Update::new((1..=7).collect());
+        assert_eq!(update.all_valid_orderings(&[]).len(), MAX_ORDERINGS);
+    }
+
     #[test]
     fn test_update_is_valid() {
         let print_queue = create_test_print_queue();
@@ -355,8 +634,113 @@ mod tests {
     #[test]
     fn test_solve_part_2() {
         let print_queue: PrintQueue = TEST_STRING.parse().unwrap();
-        let actual = solve_part_2(&print_queue);
+        let actual = solve_part_2(&print_queue).unwrap();
 
         assert_eq!(123, actual);
     }
+
+    #[test]
+    fn test_middle_sums() {
+        let print_queue = create_test_print_queue();
+        let actual = print_queue.middle_sums().unwrap();
+
+        assert_eq!(actual, (143, 123));
+    }
+
+    #[test]
+    fn test_relevant_rules() {
+        let print_queue = create_test_print_queue();
+        let update = Update(vec![75, 29, 13]);
+
+        let relevant = update.relevant_rules(&print_queue.rules);
+
+        assert_eq!(
+            relevant,
+            vec![
+                OrderingRule::new(75, 29),
+                OrderingRule::new(29, 13),
+                OrderingRule::new(75, 13),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sort_by_rules() {
+        let print_queue = create_test_print_queue();
+        let update = &print_queue.updates[3];
+
+        let sorted = update.sort_by_rules(&print_queue.rules).unwrap();
+        let corrected = update.correct_update(&print_queue.rules).unwrap();
+
+        assert_eq!(sorted, Update(vec![97, 75, 47, 61, 53]));
+        assert_eq!(
+            sorted.get_middle_page(MiddlePolicy::RequireOdd),
+            corrected.get_middle_page(MiddlePolicy::RequireOdd)
+        );
+    }
+
+    #[test]
+    fn test_sort_by_rules_partial_order() {
+        // Nothing says where 3 goes
+        let rules = vec![OrderingRule::new(1, 2)];
+        assert!(matches!(
+            Update(vec![2, 3, 1]).sort_by_rules(&rules),
+            Err(PrintQueueError::NotTotallyOrdered)
+        ));
+
+        // Every pair has a rule, but they go round in a circle
+        let rules = vec![
+            OrderingRule::new(1, 2),
+            OrderingRule::new(2, 3),
+            OrderingRule::new(3, 1),
+        ];
+        assert!(matches!(
+            Update(vec![1, 2, 3]).sort_by_rules(&rules),
+            Err(PrintQueueError::NotTotallyOrdered)
+        ));
+    }
+
+    #[test]
+    fn test_middle_policy() {
+        let update = Update(vec![75, 47, 61, 53]);
+
+        assert_eq!(update.get_middle_page(MiddlePolicy::LowerMiddle), 47);
+        assert_eq!(update.get_middle_page(MiddlePolicy::UpperMiddle), 61);
+
+        let input = "47|53\n\n75,47,61,53";
+        assert!(matches!(
+            input.parse::<PrintQueue>(),
+            Err(PrintQueueError::MalformedUpdate(_))
+        ));
+        let print_queue = PrintQueue::parse_with_policy(input, MiddlePolicy::LowerMiddle).unwrap();
+        assert_eq!(solve_part_1(&print_queue), 47);
+
+        // Empty updates are rejected whatever the policy
+        let input = "47|53\n\n75,47,61\n\n53,29,13";
+        assert!(matches!(
+            PrintQueue::parse_with_policy(input, MiddlePolicy::LowerMiddle),
+            Err(PrintQueueError::MalformedUpdate(_))
+        ));
+    }
+
+    #[test]
+    fn test_has_consistent_ordering() {
+        let mut print_queue = create_test_print_queue();
+        assert!(print_queue.has_consistent_ordering());
+
+        // Contradicts 47|13
+        print_queue.rules.push(OrderingRule::new(13, 47));
+        assert!(!print_queue.has_consistent_ordering());
+    }
+
+    #[test]
+    fn test_correct_update_cycle() {
+        let rules = vec![OrderingRule::new(1, 2), OrderingRule::new(2, 1)];
+        let update = Update(vec![1, 2, 3]);
+
+        assert!(matches!(
+            update.correct_update(&rules),
+            Err(PrintQueueError::CycleDetected)
+        ));
+    }
 }
@@ -1,29 +1,53 @@
-use std::collections::HashSet;
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    hash::{Hash, Hasher},
+};
 
 use anyhow::Result;
+use common::read_input;
 use rayon::prelude::*;
 use thiserror::Error;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-enum PosState {
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PosState {
     O, // Open
     X, // Closed
 }
 
 /// 2d top down map of the patrol area.
 /// Origin point in the upper left position.
-/// Assumed to be a regular in shape, i.e. all rows are the same length
-#[derive(Debug, PartialEq, Eq, Clone)]
-struct Map(Vec<Vec<PosState>>);
+/// Regular in shape, i.e. all rows are the same length
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub struct Map(Vec<Vec<PosState>>);
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MapError {
+    #[error("Map has no positions")]
+    Empty,
+    #[error("Row {0} is {1} long, expected {2}")]
+    RaggedRow(usize, usize, usize),
+}
 
 impl Map {
+    /// Build a map from its rows, top to bottom.
+    /// Fails if there are no positions, or if the rows aren't all the same length.
+    pub fn from_rows(rows: Vec<Vec<PosState>>) -> Result<Self, MapError> {
+        let width = rows.first().map_or(0, Vec::len);
+        if width == 0 {
+            return Err(MapError::Empty);
+        }
+        if let Some((y, row)) = rows.iter().enumerate().find(|(_, row)| row.len() != width) {
+            return Err(MapError::RaggedRow(y, row.len(), width));
+        }
+        Ok(Map(rows))
+    }
     /// Get the number of rows
     fn height(&self) -> usize {
         self.0.len()
     }
     /// Get the number of columns
     fn width(&self) -> usize {
-        self.0[0].len()
+        self.0.first().map_or(0, Vec::len)
     }
     fn get_position(&self, x: usize, y: usize) -> Option<PosState> {
         // Check for OOB
@@ -33,9 +57,17 @@ impl Map {
 
         Some(self.0[y][x])
     }
-    fn set(&mut self, x: usize, y: usize, pos_state: PosState) {
+    pub fn set(&mut self, x: usize, y: usize, pos_state: PosState) {
         self.0[y][x] = pos_state
     }
+    /// Combine the map and the guard into a single hash.
+    /// Identical maps with identical guards always produce the same signature.
+    pub fn state_signature(&self, guard: Guard) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        guard.hash(&mut hasher);
+        hasher.finish()
+    }
 }
 
 enum Translation {
@@ -54,7 +86,7 @@ impl Translation {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-enum Heading {
+pub enum Heading {
     N,
     E,
     W,
@@ -62,6 +94,11 @@ enum Heading {
 }
 
 impl Heading {
+    /// All four headings, clockwise from north, the same order the guard turns in.
+    pub fn all() -> [Self; 4] {
+        [Heading::N, Heading::E, Heading::S, Heading::W]
+    }
+
     fn turn(self) -> Self {
         match self {
             Heading::N => Heading::E,
@@ -81,19 +118,33 @@ impl Heading {
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-struct Guard {
+pub struct Guard {
     position: (usize, usize),
     heading: Heading,
 }
 
 impl Guard {
-    fn advance(self, map: &Map) -> Option<Guard> {
+    pub fn new(position: (usize, usize), heading: Heading) -> Self {
+        Guard { position, heading }
+    }
+    pub fn position(&self) -> (usize, usize) {
+        self.position
+    }
+    pub fn heading(&self) -> Heading {
+        self.heading
+    }
+    /// Step forward, or turn if the way ahead is blocked. Returns None once the guard leaves the map.
+    /// The extra position, if there is one, is treated as an obstacle so new ones can be tried without touching the map.
+    fn advance_with(self, map: &Map, extra: Option<(usize, usize)>) -> Option<Guard> {
         let (x, y) = self.position;
         let translation = self.heading.get_translation();
         // Returns None if the new position underflows, represents leaving the map.
         let (new_x, new_y) = translation.apply_translation(x, y)?;
         // Returns None if the position was out of bounds, represents leaving the map.
-        let position_type = map.get_position(new_x, new_y)?;
+        let mut position_type = map.get_position(new_x, new_y)?;
+        if extra == Some((new_x, new_y)) {
+            position_type = PosState::X;
+        }
 
         match position_type {
             // If the position is open, then enter the new position.
@@ -108,6 +159,38 @@ impl Guard {
             }),
         }
     }
+    /// Advance straight ahead by up to n steps, without turning.
+    /// Stops early in front of an obstacle or at the edge of the map.
+    /// Returns the new guard and the number of steps actually taken.
+    pub fn advance_n(self, map: &Map, n: usize) -> (Guard, usize) {
+        let (x, y) = self.position;
+        // Number of open tiles between the guard and the next obstacle or edge in the heading direction.
+        let clear = match self.heading {
+            Heading::N => (0..y)
+                .rev()
+                .take_while(|&y| map.0[y][x] == PosState::O)
+                .count(),
+            Heading::S => (y + 1..map.height())
+                .take_while(|&y| map.0[y][x] == PosState::O)
+                .count(),
+            Heading::W => (0..x)
+                .rev()
+                .take_while(|&x| map.0[y][x] == PosState::O)
+                .count(),
+            Heading::E => (x + 1..map.width())
+                .take_while(|&x| map.0[y][x] == PosState::O)
+                .count(),
+        };
+        let steps = clear.min(n);
+        let position = match self.heading {
+            Heading::N => (x, y - steps),
+            Heading::S => (x, y + steps),
+            Heading::W => (x - steps, y),
+            Heading::E => (x + steps, y),
+        };
+
+        (Guard { position, ..self }, steps)
+    }
 }
 
 #[derive(Debug, Error)]
@@ -116,9 +199,22 @@ enum ParsingError {
     InvalidCharacter(char),
     #[error("No guard found in input")]
     MissingGuard,
+    #[error("More than one guard found in input, the second at ({0}, {1})")]
+    MultipleGuards(usize, usize),
+    #[error(transparent)]
+    InvalidMap(#[from] MapError),
 }
 
 fn parse_input(input: &str) -> Result<(Map, Guard), ParsingError> {
+    parse_input_with_wall(input, None)
+}
+
+/// Same as `parse_input`, but the alternate wall character, if there is one, is also read as an obstacle.
+/// The guard can start facing any direction, shown by one of `^`, `>`, `v` or `<`.
+fn parse_input_with_wall(
+    input: &str,
+    alternate_wall: Option<char>,
+) -> Result<(Map, Guard), ParsingError> {
     // Split the input into rows
     let lines = input.trim().lines();
 
@@ -128,18 +224,35 @@ fn parse_input(input: &str) -> Result<(Map, Guard), ParsingError> {
     for (y, line) in lines.enumerate() {
         let mut row = Vec::new();
         for (x, symbol) in line.trim().char_indices() {
-            match symbol {
-                '.' => row.push(PosState::O),
-                '#' => row.push(PosState::X),
-                '^' => {
+            let heading = match symbol {
+                '.' => {
                     row.push(PosState::O);
-                    guard = Some(Guard {
-                        position: (x, y),
-                        heading: Heading::N,
-                    });
+                    continue;
+                }
+                '#' => {
+                    row.push(PosState::X);
+                    continue;
+                }
+                _ if Some(symbol) == alternate_wall => {
+                    row.push(PosState::X);
+                    continue;
                 }
+                '^' => Heading::N,
+                '>' => Heading::E,
+                'v' => Heading::S,
+                '<' => Heading::W,
                 _ => return Err(ParsingError::InvalidCharacter(symbol)),
+            };
+
+            // The guard is standing on an open position
+            row.push(PosState::O);
+            if guard.is_some() {
+                return Err(ParsingError::MultipleGuards(x, y));
             }
+            guard = Some(Guard {
+                position: (x, y),
+                heading,
+            });
         }
         map.push(row);
     }
@@ -148,11 +261,11 @@ fn parse_input(input: &str) -> Result<(Map, Guard), ParsingError> {
         return Err(ParsingError::MissingGuard);
     };
 
-    Ok((Map(map), guard))
+    Ok((Map::from_rows(map)?, guard))
 }
 
 fn main() -> Result<()> {
-    let raw_input = std::fs::read_to_string("input.txt")?;
+    let raw_input = read_input(std::env::args().nth(1))?;
     let (map, guard) = parse_input(&raw_input)?;
 
     // Part 1
@@ -166,128 +279,77 @@ fn main() -> Result<()> {
     Ok(())
 }
 
-fn solve_part_1(map: &Map, mut guard: Guard) -> usize {
-    // Unique visited positions.
-    let mut visited = HashSet::new();
-    // Initialize visited with the starting position.
-    visited.insert(guard.position);
+/// How a walk around the map ended.
+#[derive(Debug, PartialEq)]
+enum WalkResult {
+    /// The guard left the map, after visiting these positions.
+    Exited(HashSet<(usize, usize)>),
+    /// The guard ended up somewhere it had already been, facing the same way.
+    Looped,
+}
 
-    while let Some(new_guard) = guard.advance(map) {
+/// Walk the guard around the map until they either leave it or get stuck in a loop.
+/// The extra position, if there is one, is treated as an obstacle without changing the map.
+fn walk(map: &Map, mut guard: Guard, extra: Option<(usize, usize)>) -> WalkResult {
+    // Unique visited positions, starting with where the guard is now.
+    let mut visited = HashSet::from([guard.position]);
+    // A guard that comes back to the same position and heading will go round forever.
+    let mut states = HashSet::from([guard]);
+
+    while let Some(new_guard) = guard.advance_with(map, extra) {
+        if !states.insert(new_guard) {
+            return WalkResult::Looped;
+        }
         visited.insert(new_guard.position);
-
-        // println!(
-        //     "({},{}), {:?}",
-        //     guard.position.0, guard.position.1, guard.heading
-        // );
-
         guard = new_guard
     }
 
-    visited.len()
+    WalkResult::Exited(visited)
 }
 
-// Brute force our way through this
-fn solve_part_2(mut map: Map, guard: Guard) -> usize {
-    let mut count = 0;
-    let mut call_counter = 0;
-    // This is an optimization where the original path is collected, so that only traversed tiles are checked.
-    let mut path = HashSet::new();
-    let mut origin = guard;
-    while let Some(current) = origin.advance(&map) {
-        path.insert(current.position);
-        origin = current
+fn solve_part_1(map: &Map, guard: Guard) -> usize {
+    match walk(map, guard, None) {
+        WalkResult::Exited(visited) => visited.len(),
+        // A guard that never leaves doesn't have an answer.
+        WalkResult::Looped => 0,
     }
+}
 
-    for (x, y) in path {
-        let old = map.get_position(x, y).unwrap();
-
-        // Don't bother with closed squares or the origin point
-        if old != PosState::O || (x, y) == guard.position {
-            continue;
-        }
-
-        call_counter += 1;
-
-        let mut guard = guard;
-
-        let mut visited = HashSet::new();
-        // Set the square to be an obstacle
-        map.set(x, y, PosState::X);
-
-        visited.insert(guard);
-
-        while let Some(current) = guard.advance(&map) {
-            let loop_point = !visited.insert(current);
-
-            if loop_point {
-                count += 1;
-                break;
-            }
+/// Collect the positions the guard walks through on the original map, skipping their starting position.
+/// These are the only places where a new obstacle could make a difference.
+fn candidate_obstacles(map: &Map, guard: Guard) -> HashSet<(usize, usize)> {
+    let mut path = match walk(map, guard, None) {
+        WalkResult::Exited(visited) => visited,
+        WalkResult::Looped => HashSet::new(),
+    };
+    path.remove(&guard.position);
+    path
+}
 
-            guard = current
-        }
+// Brute force our way through this
+pub fn solve_part_2(map: Map, guard: Guard) -> usize {
+    // This is an optimization where the original path is collected, so that only traversed tiles are checked.
+    let path = candidate_obstacles(&map, guard);
 
-        // Set the square back again
-        map.set(x, y, PosState::O);
-    }
+    let count = path
+        .iter()
+        .filter(|&&candidate| walk(&map, guard, Some(candidate)) == WalkResult::Looped)
+        .count();
 
-    println!("{} positions were examined", call_counter);
+    println!("{} positions were examined", path.len());
 
     count
 }
 
 fn solve_part_2_par(map: Map, guard: Guard) -> usize {
     // This is an optimization where the original path is collected, so that only traversed tiles are checked.
-    let mut path = HashSet::new();
-    let mut origin = guard;
+    let path = candidate_obstacles(&map, guard);
 
-    while let Some(current) = origin.advance(&map) {
-        path.insert(current.position);
-        origin = current;
-    }
-
-    // Parallelize the `for` loop using Rayon
-    let results: Vec<usize> = path
+    // Parallelize the check using Rayon, the map is never modified so it can be shared
+    let count = path
         .par_iter()
-        .filter_map(|&(x, y)| {
-            let old = map.get_position(x, y).unwrap();
-
-            // Skip closed squares or the origin point
-            if old != PosState::O || (x, y) == guard.position {
-                return None;
-            }
-
-            let mut map_clone = map.clone(); // Clone the map for independent processing
-            let mut guard = guard;
-
-            let mut visited = HashSet::new();
-
-            // Set the square to be an obstacle
-            map_clone.set(x, y, PosState::X);
-
-            visited.insert(guard);
-
-            while let Some(current) = guard.advance(&map_clone) {
-                let loop_point = !visited.insert(current);
-
-                if loop_point {
-                    // Restore the map to its original state before exiting
-                    map_clone.set(x, y, PosState::O);
-                    return Some(1); // Found a loop
-                }
-
-                guard = current;
-            }
-
-            // Restore the map to its original state
-            map_clone.set(x, y, PosState::O);
-
-            None // No loop found
-        })
-        .collect();
-
-    // Aggregate results from all parallel computations
-    let count = results.into_iter().sum::<usize>();
+        .filter(|&&candidate| walk(&map, guard, Some(candidate)) == WalkResult::Looped)
+        .count();
 
     println!("{} positions were examined", path.len());
 
@@ -344,6 +406,24 @@ fn _sq_len(a: (usize, usize), b: (usize, usize)) -> usize {
 mod test {
     use super::*;
 
+    #[test]
+    fn test_heading_all() {
+        let all = Heading::all();
+        for heading in all {
+            assert_eq!(all.iter().filter(|&&h| h == heading).count(), 1);
+            // Turning steps through the headings in order
+            let next = all[(all.iter().position(|&h| h == heading).unwrap() + 1) % 4];
+            assert_eq!(heading.turn(), next);
+        }
+
+        // One step each way ends up back where it started
+        let origin = (3, 3);
+        let end = all.iter().try_fold(origin, |(x, y), heading| {
+            heading.get_translation().apply_translation(x, y)
+        });
+        assert_eq!(end, Some(origin));
+    }
+
     const TEST_INPUT: &str = r#"        
         ....#.....
         .........#
@@ -387,6 +467,43 @@ mod test {
         assert_eq!(expected_guard, actual_guard);
     }
 
+    #[test]
+    fn test_parse_guard_headings() {
+        let (map, guard) = parse_input("...\n.>.\n..#").unwrap();
+        assert_eq!(guard.position, (1, 1));
+        assert_eq!(guard.heading, Heading::E);
+        assert_eq!(map.get_position(1, 1), Some(PosState::O));
+
+        for (symbol, heading) in [('^', Heading::N), ('v', Heading::S), ('<', Heading::W)] {
+            let (_, guard) = parse_input(&format!(".{}", symbol)).unwrap();
+            assert_eq!(guard.heading, heading);
+        }
+
+        assert!(matches!(
+            parse_input(".>.\n..^"),
+            Err(ParsingError::MultipleGuards(2, 1))
+        ));
+        assert!(matches!(
+            parse_input("..."),
+            Err(ParsingError::MissingGuard)
+        ));
+        assert!(matches!(
+            parse_input("...\n.^"),
+            Err(ParsingError::InvalidMap(MapError::RaggedRow(1, 2, 3)))
+        ));
+    }
+
+    #[test]
+    fn test_parse_alternate_wall() {
+        let (map, _) = parse_input_with_wall("O.\n^#", Some('O')).unwrap();
+        assert_eq!(map.get_position(0, 0), Some(PosState::X));
+        assert_eq!(map.get_position(1, 1), Some(PosState::X));
+        assert!(matches!(
+            parse_input("O.\n^#"),
+            Err(ParsingError::InvalidCharacter('O'))
+        ));
+    }
+
     #[test]
     fn test_get_position() {
         let map = create_test_map();
@@ -414,6 +531,32 @@ mod test {
         assert_eq!(41, actual);
     }
 
+    #[test]
+    fn test_public_api() {
+        use PosState::{O, X};
+        let map = Map::from_rows(vec![vec![O, X, O], vec![O, O, X], vec![O, O, O]]).unwrap();
+        let guard = Guard::new((1, 2), Heading::N);
+
+        assert_eq!(guard.position(), (1, 2));
+        assert_eq!(guard.heading(), Heading::N);
+        // Up one, then walls ahead and to the right turn the guard around and off the bottom
+        assert_eq!(solve_part_1(&map, guard), 2);
+        assert_eq!(
+            Map::from_rows(create_test_map().0).unwrap(),
+            create_test_map()
+        );
+        assert_eq!(Map::from_rows(vec![]), Err(MapError::Empty));
+        assert_eq!(Map::from_rows(vec![vec![]]), Err(MapError::Empty));
+        assert_eq!(
+            Map::from_rows(vec![vec![O, O], vec![O]]),
+            Err(MapError::RaggedRow(1, 1, 2))
+        );
+        assert_eq!(
+            solve_part_1(&create_test_map(), Guard::new((4, 6), Heading::N)),
+            41
+        );
+    }
+
     #[test]
     fn test_solve_part_2() {
         let map = create_test_map();
@@ -427,6 +570,98 @@ mod test {
         assert_eq!(6, actual);
     }
 
+    #[test]
+    fn test_walk() {
+        let map = create_test_map();
+        let guard = Guard {
+            position: (4, 6),
+            heading: Heading::N,
+        };
+
+        // Left alone, the guard walks off the bottom of the map
+        let WalkResult::Exited(visited) = walk(&map, guard, None) else {
+            panic!("guard should leave the map");
+        };
+        assert_eq!(visited.len(), 41);
+        assert!(visited.contains(&(4, 6)));
+        assert!(visited.contains(&(7, 9)));
+
+        // Every known loop obstacle from the puzzle description
+        for extra in [(3, 6), (6, 7), (7, 7), (1, 8), (3, 8), (7, 9)] {
+            assert_eq!(walk(&map, guard, Some(extra)), WalkResult::Looped);
+        }
+        // An obstacle off the path changes nothing
+        assert_eq!(walk(&map, guard, Some((0, 0))), WalkResult::Exited(visited));
+        // The map itself is never modified
+        assert_eq!(map, create_test_map());
+        assert_eq!(solve_part_2_par(map, guard), 6);
+    }
+
+    #[test]
+    fn test_state_signature() {
+        let map = create_test_map();
+        let guard = Guard {
+            position: (4, 6),
+            heading: Heading::N,
+        };
+        let mut modified = map.clone();
+        modified.set(0, 0, PosState::X);
+        let turned = Guard {
+            heading: Heading::E,
+            ..guard
+        };
+
+        assert_eq!(
+            map.state_signature(guard),
+            create_test_map().state_signature(guard)
+        );
+        assert_ne!(map.state_signature(guard), modified.state_signature(guard));
+        assert_ne!(map.state_signature(guard), map.state_signature(turned));
+    }
+
+    #[test]
+    fn test_advance_n() {
+        let map = create_test_map();
+        let guard = Guard {
+            position: (4, 6),
+            heading: Heading::N,
+        };
+        let at_wall = Guard {
+            position: (4, 1),
+            ..guard
+        };
+
+        // Jumps straight to the wall, no matter how far it is asked to go.
+        assert_eq!(guard.advance_n(&map, 100), (at_wall, 5));
+        assert_eq!(at_wall.advance_n(&map, 100), (at_wall, 0));
+        // Stops short when asked to.
+        assert_eq!(
+            guard.advance_n(&map, 2),
+            (
+                Guard {
+                    position: (4, 4),
+                    ..guard
+                },
+                2
+            )
+        );
+        // Stops at the edge of the map.
+        let east = Guard {
+            position: (2, 2),
+            heading: Heading::E,
+        };
+        assert_eq!(
+            east.advance_n(&map, 100),
+            (
+                Guard {
+                    position: (9, 2),
+                    ..east
+                },
+                7
+            )
+        );
+    }
+
     #[test]
     fn test_is_right_angle_triangle() {
         let (a, b, c) = ((4, 1), (8, 1), (8, 6));
@@ -1,8 +1,10 @@
 use anyhow::Result;
+use common::read_input;
+use rayon::prelude::*;
 use thiserror::Error;
 
 #[derive(Debug, PartialEq)]
-struct Equation {
+pub struct Equation {
     outcome: u64,
     values: Vec<u64>,
 }
@@ -59,6 +61,50 @@ fn concatenate(a: u64, b: u64) -> u64 {
     a * shifted + b
 }
 
+/// Prepends the digits of b to a, the mirror image of `concatenate`
+/// A zero b is still a digit, but a leading zero doesn't change the value
+fn concatenate_reverse(a: u64, b: u64) -> u64 {
+    concatenate(b, a)
+}
+
+/// An operator that can be placed between two values
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    Add,
+    Multiply,
+    Concat,
+    ConcatRev,
+}
+
+impl Op {
+    fn apply(self, a: u64, b: u64) -> u64 {
+        match self {
+            Op::Add => a + b,
+            Op::Multiply => a * b,
+            Op::Concat => concatenate(a, b),
+            Op::ConcatRev => concatenate_reverse(a, b),
+        }
+    }
+}
+
+/// Evaluate the given values to see if they can be combined to reach the target, using only the given operators
+/// Operators are applied left to right, starting from the first value
+pub fn evaluate_with_ops(values: &[u64], target: u64, ops: &[Op]) -> bool {
+    /// Recursively try every operator between the current result and the next value
+    fn inner(values: &[u64], target: u64, current: u64, ops: &[Op]) -> bool {
+        let Some((first, rest)) = values.split_first() else {
+            return current == target;
+        };
+        ops.iter()
+            .any(|op| inner(rest, target, op.apply(current, *first), ops))
+    }
+
+    let Some((first, rest)) = values.split_first() else {
+        return false;
+    };
+    inner(rest, target, *first, ops)
+}
+
 #[derive(Debug, Error)]
 enum ParseError {
     #[error("Failed to parse integer: {0}")]
@@ -91,7 +137,7 @@ fn parse_input(input: &str) -> Result<Vec<Equation>, ParseError> {
 }
 
 fn main() -> Result<()> {
-    let raw_input = std::fs::read_to_string("input.txt")?;
+    let raw_input = read_input(std::env::args().nth(1))?;
     let equations = parse_input(&raw_input)?;
 
     // Part 1
@@ -121,7 +167,27 @@ fn part_2(equations: &[Equation]) -> u64 {
         .sum()
 }
 
+/// Same as `part_1`, but the equations are checked in parallel
+pub fn part_1_par(equations: &[Equation]) -> u64 {
+    equations
+        .par_iter()
+        .filter(|eq| eq.validate())
+        .map(|eq| eq.outcome)
+        .sum()
+}
+
+/// Same as `part_2`, but the equations are checked in parallel
+pub fn part_2_par(equations: &[Equation]) -> u64 {
+    equations
+        .par_iter()
+        .filter(|eq| eq.validate_with_concatenate())
+        .map(|eq| eq.outcome)
+        .sum()
+}
+
 #[cfg(test)]
+// The fixtures are written out longhand on purpose
+#[allow(clippy::vec_init_then_push, clippy::useless_vec)]
 mod tests {
     use super::*;
 
@@ -211,6 +277,16 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parts_par() {
+        let equations = test_equations();
+
+        assert_eq!(part_1_par(&equations), part_1(&equations));
+        assert_eq!(part_1_par(&equations), 3749);
+        assert_eq!(part_2_par(&equations), part_2(&equations));
+        assert_eq!(part_2_par(&equations), 11387);
+    }
+
     #[test]
     fn test_concatenate() {
         assert_eq!(concatenate(123, 456), 123456);
@@ -218,6 +294,40 @@ mod tests {
         assert_eq!(concatenate(123, 0), 1230);
     }
 
+    #[test]
+    fn test_concatenate_reverse() {
+        assert_eq!(concatenate_reverse(45, 123), 12345);
+        assert_eq!(concatenate_reverse(0, 123), 1230);
+        assert_eq!(concatenate_reverse(45, 0), 45);
+        assert_eq!(concatenate_reverse(0, 0), 0);
+    }
+
+    #[test]
+    fn test_evaluate_with_ops() {
+        let all = [Op::Add, Op::Multiply, Op::Concat];
+
+        // Same results as the dedicated evaluators
+        for equation in test_equations() {
+            assert_eq!(
+                evaluate_with_ops(&equation.values, equation.outcome, &all[..2]),
+                equation.validate()
+            );
+            assert_eq!(
+                evaluate_with_ops(&equation.values, equation.outcome, &all),
+                equation.validate_with_concatenate()
+            );
+        }
+
+        // 15 || 6 reversed is 615
+        assert!(!evaluate_with_ops(&[15, 6], 615, &all));
+        assert!(evaluate_with_ops(&[15, 6], 615, &[Op::ConcatRev]));
+        // (1 prepended to 2) prepended to 3, or (1 + 2) prepended to 3
+        let ops = [Op::Add, Op::ConcatRev];
+        assert!(evaluate_with_ops(&[1, 2, 3], 321, &ops));
+        assert!(evaluate_with_ops(&[1, 2, 3], 33, &ops));
+        assert!(!evaluate_with_ops(&[1, 2, 3], 123, &ops));
+    }
+
     #[test]
     fn test_evaluate_with_concatenate() {
         let known_eqs = test_known_equations();
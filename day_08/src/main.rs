@@ -1,11 +1,13 @@
 use std::collections::{HashMap, HashSet};
 
 use anyhow::Result;
+use common::read_input;
+use image::{ImageBuffer, Rgb};
 use itertools::Itertools;
 use thiserror::Error;
 
 #[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
-struct Coordinate {
+pub struct Coordinate {
     x: i32,
     y: i32,
 }
@@ -21,45 +23,157 @@ impl Coordinate {
 }
 
 #[derive(Debug, PartialEq)]
-struct Map {
+pub struct Map {
     width: usize,
     height: usize,
-    antennas: Vec<Vec<Coordinate>>,
+    antennas: HashMap<char, Vec<Coordinate>>,
 }
 
 impl Map {
     fn count_unique_antinodes(&self) -> usize {
-        let mut antinodes = HashSet::new();
+        self.antinodes().len()
+    }
 
-        for antenna_frequency in &self.antennas {
-            for (a, b) in antenna_frequency.iter().tuple_combinations() {
-                let (c, d) = calculate_antinodes(*a, *b);
-                if self.in_bounds(c) {
-                    antinodes.insert(c);
-                }
-                if self.in_bounds(d) {
-                    antinodes.insert(d);
-                }
+    fn antinodes(&self) -> HashSet<Coordinate> {
+        self.antennas
+            .keys()
+            .flat_map(|&frequency| self.antinodes_for_frequency(frequency))
+            .collect()
+    }
+
+    // Antinodes created by a single frequency, empty if there are no antennas with that frequency
+    fn antinodes_for_frequency(&self, frequency: char) -> HashSet<Coordinate> {
+        let mut antinodes = HashSet::new();
+        let Some(antennas) = self.antennas.get(&frequency) else {
+            return antinodes;
+        };
+
+        for (a, b) in antennas.iter().tuple_combinations() {
+            let (c, d) = calculate_antinodes(*a, *b);
+            if self.in_bounds(c) {
+                antinodes.insert(c);
+            }
+            if self.in_bounds(d) {
+                antinodes.insert(d);
             }
         }
 
-        antinodes.len()
+        antinodes
     }
 
     fn count_unique_resonant_antinodes(&self) -> usize {
+        self.resonant_antinodes().len()
+    }
+
+    fn resonant_antinodes(&self) -> HashSet<Coordinate> {
+        self.antennas
+            .keys()
+            .flat_map(|&frequency| self.resonant_antinodes_for_frequency(frequency))
+            .collect()
+    }
+
+    // Resonant antinodes created by a single frequency, empty if there are no antennas with that frequency
+    fn resonant_antinodes_for_frequency(&self, frequency: char) -> HashSet<Coordinate> {
         let mut antinodes = HashSet::new();
+        let Some(antennas) = self.antennas.get(&frequency) else {
+            return antinodes;
+        };
+
+        for (a, b) in antennas.iter().tuple_combinations() {
+            let resonant_antinodes = calculate_resonant_antinodes(*a, *b, self.width, self.height);
+            for antinode in resonant_antinodes {
+                antinodes.insert(antinode);
+            }
+        }
+
+        antinodes
+    }
+
+    // Antennas are drawn with their frequency, antinodes as '#' and any antinode sharing a tile with an antenna as '*'
+    pub fn render(&self, antinodes: &HashSet<Coordinate>) -> String {
+        let labels = self.labels();
+
+        let mut output = String::with_capacity((self.width + 1) * self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let coordinate = Coordinate::new(x as i32, y as i32);
+                let c = match (labels.get(&coordinate), antinodes.contains(&coordinate)) {
+                    (Some(_), true) => '*',
+                    (Some(&frequency), false) => frequency,
+                    (None, true) => '#',
+                    (None, false) => '.',
+                };
+                output.push(c);
+            }
+            output.push('\n');
+        }
+
+        output
+    }
+
+    // Same layout as `render`, but saved as a bitmap with one pixel per tile, creating any missing directories.
+    // Each frequency gets its own colour and antinodes on top of an antenna are yellow.
+    // Other antinodes fade from red to blue the further they are from their source antennas, see `harmonic_indices`.
+    pub fn save_bmp(&self, antinodes: &HashSet<Coordinate>, filename: &str) -> Result<()> {
+        let labels = self.labels();
+        let harmonics = self.harmonic_indices();
+        // Anything that isn't on a resonant line can only be a first harmonic
+        let harmonic = |coordinate: &Coordinate| harmonics.get(coordinate).copied().unwrap_or(1);
+        let max_harmonic = antinodes.iter().map(harmonic).max().unwrap_or(1);
+        let mut img = ImageBuffer::new(self.width as u32, self.height as u32);
+
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            let coordinate = Coordinate::new(x as i32, y as i32);
+            *pixel = match (labels.get(&coordinate), antinodes.contains(&coordinate)) {
+                (Some(_), true) => Rgb([255u8, 220, 0]),
+                (Some(&frequency), false) => frequency_color(frequency),
+                (None, true) => harmonic_color(harmonic(&coordinate), max_harmonic),
+                // Checkerboard the empty tiles so the grid is visible
+                (None, false) if (x + y) % 2 == 0 => Rgb([255u8, 255, 255]),
+                (None, false) => Rgb([225u8, 225, 225]),
+            };
+        }
+
+        if let Some(parent) = std::path::Path::new(filename).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        img.save(filename)?;
+        Ok(())
+    }
 
-        for antenna_frequency in &self.antennas {
-            for (a, b) in antenna_frequency.iter().tuple_combinations() {
-                let resonant_antinodes =
-                    calculate_resonant_antinodes(*a, *b, self.width, self.height);
-                for antinode in resonant_antinodes {
-                    antinodes.insert(antinode);
+    // Maps every resonant antinode to its harmonic index, the number of steps to the nearest antenna that created it.
+    // Antennas are harmonic 0 and the part 1 antinodes are harmonic 1. Where pairs overlap, the lowest index wins.
+    fn harmonic_indices(&self) -> HashMap<Coordinate, usize> {
+        let mut indices = HashMap::new();
+        for antennas in self.antennas.values() {
+            for (a, b) in antennas.iter().tuple_combinations() {
+                let (dx, dy) = (b.x - a.x, b.y - a.y);
+                for c in calculate_resonant_antinodes(*a, *b, self.width, self.height) {
+                    // Steps from a towards b, so b is at 1
+                    let n = if dx != 0 {
+                        (c.x - a.x) / dx
+                    } else {
+                        (c.y - a.y) / dy
+                    };
+                    let index = if n >= 1 { n - 1 } else { -n } as usize;
+                    indices
+                        .entry(c)
+                        .and_modify(|i: &mut usize| *i = (*i).min(index))
+                        .or_insert(index);
                 }
             }
         }
 
-        antinodes.len()
+        indices
+    }
+
+    // Maps the position of every antenna to its frequency
+    fn labels(&self) -> HashMap<Coordinate, char> {
+        self.antennas
+            .iter()
+            .flat_map(|(&frequency, coordinates)| coordinates.iter().map(move |&c| (c, frequency)))
+            .collect()
     }
 
     fn in_bounds(&self, coordinate: Coordinate) -> bool {
@@ -67,6 +181,20 @@ impl Map {
     }
 }
 
+// Picks a dark colour for a frequency, spreading neighbouring characters apart so they are easy to tell apart
+fn frequency_color(frequency: char) -> Rgb<u8> {
+    let n = frequency as u32;
+    let channel = |multiplier: u32| ((n * multiplier) % 160) as u8;
+    Rgb([channel(37), channel(59), 40 + channel(83)])
+}
+
+// Fades from red at the first harmonic to blue at the highest one
+fn harmonic_color(index: usize, max_index: usize) -> Rgb<u8> {
+    let t = index.saturating_sub(1) as f32 / max_index.saturating_sub(1).max(1) as f32;
+    let lerp = |from: f32, to: f32| (from + (to - from) * t.min(1.0)).round() as u8;
+    Rgb([lerp(220.0, 40.0), 0, lerp(0.0, 220.0)])
+}
+
 fn calculate_antinodes(a: Coordinate, b: Coordinate) -> (Coordinate, Coordinate) {
     // Calculate the difference
     let dx = b.x - a.x;
@@ -116,6 +244,26 @@ fn calculate_resonant_antinodes(
     antinodes
 }
 
+// True if p is a whole number of (dx, dy) steps away from a, on the line through a and b
+pub fn is_resonant_antinode(a: Coordinate, b: Coordinate, p: Coordinate) -> bool {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let (px, py) = (p.x - a.x, p.y - a.y);
+
+    if dx == 0 && dy == 0 {
+        return px == 0 && py == 0;
+    }
+    // Collinear, when the cross product is zero
+    if px * dy != py * dx {
+        return false;
+    }
+    // On the lattice, when the offset is an exact multiple of the step
+    if dx != 0 {
+        px % dx == 0
+    } else {
+        py % dy == 0
+    }
+}
+
 #[derive(Debug, Error)]
 enum MapParseError {
     #[error("Invalid character in map: {0}")]
@@ -146,8 +294,6 @@ fn parse_input(input: &str) -> Result<Map, MapParseError> {
         })
     })?;
 
-    let antennas = antennas.into_values().collect();
-
     Ok(Map {
         antennas,
         width,
@@ -164,7 +310,7 @@ fn is_valid_empty(c: char) -> bool {
 }
 
 fn main() -> Result<()> {
-    let input = std::fs::read_to_string("input.txt")?;
+    let input = read_input(std::env::args().nth(1))?;
     let map = parse_input(&input)?;
 
     // Part 1
@@ -193,19 +339,25 @@ mod tests {
     fn create_test_map() -> Map {
         let width = 12;
         let height = 12;
-        let antennas = vec![
-            vec![
-                Coordinate::new(8, 1),
-                Coordinate::new(5, 2),
-                Coordinate::new(7, 3),
-                Coordinate::new(4, 4),
-            ],
-            vec![
-                Coordinate::new(6, 5),
-                Coordinate::new(8, 8),
-                Coordinate::new(9, 9),
-            ],
-        ];
+        let antennas = HashMap::from([
+            (
+                '0',
+                vec![
+                    Coordinate::new(8, 1),
+                    Coordinate::new(5, 2),
+                    Coordinate::new(7, 3),
+                    Coordinate::new(4, 4),
+                ],
+            ),
+            (
+                'A',
+                vec![
+                    Coordinate::new(6, 5),
+                    Coordinate::new(8, 8),
+                    Coordinate::new(9, 9),
+                ],
+            ),
+        ]);
 
         Map {
             width,
@@ -251,6 +403,34 @@ mod tests {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn test_is_resonant_antinode() {
+        let a = Coordinate::new(2, 1);
+        let b = Coordinate::new(1, 2);
+
+        // Every generated resonant antinode passes
+        for p in calculate_resonant_antinodes(a, b, 4, 4) {
+            assert!(is_resonant_antinode(a, b, p));
+        }
+        // Further along the line than the map goes
+        assert!(is_resonant_antinode(a, b, Coordinate::new(-2, 5)));
+
+        // Off the line
+        assert!(!is_resonant_antinode(a, b, Coordinate::new(2, 2)));
+
+        // On the line, but between lattice steps
+        let a = Coordinate::new(0, 0);
+        let b = Coordinate::new(2, 4);
+        assert!(is_resonant_antinode(a, b, Coordinate::new(4, 8)));
+        assert!(!is_resonant_antinode(a, b, Coordinate::new(1, 2)));
+        assert!(!is_resonant_antinode(a, b, Coordinate::new(3, 6)));
+
+        // Vertical line
+        let b = Coordinate::new(0, 3);
+        assert!(is_resonant_antinode(a, b, Coordinate::new(0, -6)));
+        assert!(!is_resonant_antinode(a, b, Coordinate::new(0, 4)));
+    }
+
     #[test]
     fn test_count_unique_antinodes() {
         let map = create_test_map();
@@ -266,4 +446,92 @@ mod tests {
 
         assert_eq!(actual, 34);
     }
+
+    #[test]
+    fn test_antinodes_for_frequency() {
+        let map = create_test_map();
+        let antinodes = map.antinodes_for_frequency('A');
+        let resonant_antinodes = map.resonant_antinodes_for_frequency('A');
+
+        assert_eq!(antinodes.len(), 5);
+        assert_eq!(resonant_antinodes.len(), 16);
+        assert!(map.antinodes_for_frequency('z').is_empty());
+
+        // The union over every frequency is the same as the full result
+        let union: HashSet<Coordinate> = map
+            .antennas
+            .keys()
+            .flat_map(|&f| map.antinodes_for_frequency(f))
+            .collect();
+        let resonant_union: HashSet<Coordinate> = map
+            .antennas
+            .keys()
+            .flat_map(|&f| map.resonant_antinodes_for_frequency(f))
+            .collect();
+        assert_eq!(union, map.antinodes());
+        assert_eq!(resonant_union, map.resonant_antinodes());
+        assert_eq!(union.len(), 14);
+        assert_eq!(resonant_union.len(), 34);
+    }
+
+    #[test]
+    fn test_save_bmp() {
+        let map = create_test_map();
+        let dir = std::env::temp_dir().join(format!("day_08_test_{}", std::process::id()));
+        let filename = dir.join("nested").join("resonant.bmp");
+
+        map.save_bmp(&map.resonant_antinodes(), filename.to_str().unwrap())
+            .unwrap();
+
+        let size = std::fs::metadata(&filename).unwrap().len();
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(size > 0);
+        // Frequencies have different colours
+        assert_ne!(frequency_color('0'), frequency_color('A'));
+    }
+
+    #[test]
+    fn test_harmonic_indices() {
+        let map = create_test_map();
+        let harmonics = map.harmonic_indices();
+
+        assert_eq!(harmonics.len(), map.count_unique_resonant_antinodes());
+        // Antennas are harmonic 0 and the part 1 antinodes are harmonic 1, unless they share a tile with an antenna
+        let labels = map.labels();
+        for coordinate in labels.keys() {
+            assert_eq!(harmonics[coordinate], 0);
+        }
+        for coordinate in map.antinodes() {
+            let expected = if labels.contains_key(&coordinate) {
+                0
+            } else {
+                1
+            };
+            assert_eq!(harmonics[&coordinate], expected);
+        }
+        // The 'A' antennas at (8, 8) and (9, 9) resonate down the diagonal, (3, 3) is 5 steps from (8, 8)
+        assert_eq!(harmonics[&Coordinate::new(3, 3)], 5);
+
+        // Different harmonics get different colours
+        let max = *harmonics.values().max().unwrap();
+        assert_ne!(harmonic_color(1, max), harmonic_color(2, max));
+        assert_ne!(harmonic_color(2, max), harmonic_color(max, max));
+        assert_eq!(harmonic_color(1, max), Rgb([220, 0, 0]));
+    }
+
+    #[test]
+    fn test_render() {
+        let map = create_test_map();
+        let antinodes = map.antinodes();
+        let rendered = map.render(&antinodes);
+
+        assert_eq!(rendered.lines().count(), map.height);
+        assert!(rendered.lines().all(|line| line.len() == map.width));
+
+        // One antinode in the sample shares a tile with the topmost 'A' antenna
+        let hashes = rendered.chars().filter(|&c| c == '#').count();
+        let stars = rendered.chars().filter(|&c| c == '*').count();
+        assert_eq!(stars, 1);
+        assert_eq!(hashes + stars, antinodes.len());
+    }
 }
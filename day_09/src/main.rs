@@ -1,22 +1,23 @@
 use std::collections::HashSet;
 
 use anyhow::Result;
+use common::read_input;
 
 #[derive(Debug, PartialEq)]
-struct DiskMap<T: DiskMapState> {
+pub struct DiskMap<T: DiskMapState> {
     state: T,
 }
 
-trait DiskMapState {}
+pub trait DiskMapState {}
 
 #[derive(Debug)]
-struct Raw(String);
+pub struct Raw(String);
 #[derive(Debug, PartialEq)]
-struct Parsed(Vec<usize>);
+pub struct Parsed(Vec<usize>);
 #[derive(Debug, PartialEq)]
-struct Expanded(Vec<Option<usize>>);
+pub struct Expanded(Vec<Option<usize>>);
 #[derive(Debug, PartialEq)]
-struct Compressed(Vec<Option<usize>>);
+pub struct Compressed(Vec<Option<usize>>);
 
 impl DiskMapState for Raw {}
 impl DiskMapState for Parsed {}
@@ -70,6 +71,26 @@ impl DiskMap<Parsed> {
 }
 
 impl DiskMap<Expanded> {
+    /// Iterates over the blocks in order, None being an empty block.
+    pub fn blocks(&self) -> impl Iterator<Item = Option<usize>> + '_ {
+        self.state.0.iter().copied()
+    }
+
+    /// Builds an expanded disk map directly from its blocks.
+    pub fn from_blocks(blocks: Vec<Option<usize>>) -> DiskMap<Expanded> {
+        DiskMap {
+            state: Expanded(blocks),
+        }
+    }
+
+    /// Collapses the expanded disk map back into the alternating file-gap lengths it was expanded from.
+    /// Adjacent files get a zero length gap between them. Zero length files can't be recovered.
+    pub fn to_parsed(&self) -> DiskMap<Parsed> {
+        DiskMap {
+            state: Parsed(run_lengths(&self.state.0)),
+        }
+    }
+
     /// Takes in an expanded disk map and compresses it by moving the rigthmost block id to the leftmost
     /// empty position.
     fn compress(self) -> DiskMap<Compressed> {
@@ -170,21 +191,103 @@ impl DiskMap<Expanded> {
     }
 }
 
+/// Splits the blocks into the alternating file-gap run lengths, starting with a file.
+/// Adjacent files get a zero length gap between them, and a disk starting with a gap gets a zero length file.
+fn run_lengths(blocks: &[Option<usize>]) -> Vec<usize> {
+    let mut lengths = Vec::new();
+    let mut previous = None;
+    for block in blocks {
+        let is_block = lengths.len() % 2 == 1; // The last pushed length was a file.
+        match (block, previous == Some(block)) {
+            // Still in the same run, so extend it.
+            (_, true) => *lengths.last_mut().unwrap() += 1,
+            // A new file, add a zero length gap if the last run was also a file.
+            (Some(_), false) => {
+                if is_block {
+                    lengths.push(0);
+                }
+                lengths.push(1);
+            }
+            // A new gap, add a zero length file if the disk starts with a gap.
+            (None, false) => {
+                if !is_block {
+                    lengths.push(0);
+                }
+                lengths.push(1);
+            }
+        }
+        previous = Some(block);
+    }
+    lengths
+}
+
 impl DiskMap<Compressed> {
+    /// Iterates over the blocks in order, None being an empty block.
+    pub fn blocks(&self) -> impl Iterator<Item = Option<usize>> + '_ {
+        self.state.0.iter().copied()
+    }
+
     /// Calculates the checksum of the disk map by multiplying the block id with its new position.
     fn checksum(&self) -> usize {
-        self.state.0.iter().enumerate().fold(0, |acc, (i, block)| {
-            if let Some(value) = block {
-                acc + value * i
-            } else {
-                acc
+        self.checksum_filtered(|_| true)
+    }
+
+    /// Same as checksum(), but only blocks whose id passes the `keep` filter are counted.
+    fn checksum_filtered<F: Fn(usize) -> bool>(&self, keep: F) -> usize {
+        self.blocks()
+            .enumerate()
+            .fold(0, |acc, (i, block)| match block {
+                Some(value) if keep(value) => acc + value * i,
+                _ => acc,
+            })
+    }
+
+    /// Encodes the disk back into the compact digit format, alternating file and gap lengths.
+    /// Only the run lengths are kept, so file ids are implied by order again when it's parsed.
+    /// Returns None if any run is longer than 9, since it wouldn't fit in a single digit.
+    pub fn to_compact_string(&self) -> Option<String> {
+        run_lengths(&self.state.0)
+            .into_iter()
+            .map(|length| char::from_digit(length.try_into().ok()?, 10))
+            .collect()
+    }
+
+    /// Finds every maximal run of empty blocks as `(start, length)`, in disk order.
+    /// Empty blocks are stored individually, so runs left behind by different moved files merge on their own.
+    pub fn gap_runs(&self) -> Vec<(usize, usize)> {
+        let mut runs: Vec<(usize, usize)> = Vec::new();
+        for (i, block) in self.blocks().enumerate() {
+            if block.is_some() {
+                continue;
             }
-        })
+            match runs.last_mut() {
+                // Carry on with the previous run if it ended right before this block.
+                Some((start, length)) if *start + *length == i => *length += 1,
+                _ => runs.push((i, 1)),
+            }
+        }
+        runs
+    }
+
+    /// Counts the gaps (runs of empty blocks) that come before the last file block.
+    /// Empty blocks trailing after the last file are not counted.
+    pub fn fragmentation(&self) -> usize {
+        let disk_map = &self.state.0;
+        let Some(last_file) = disk_map.iter().rposition(|block| block.is_some()) else {
+            return 0;
+        };
+
+        disk_map[..last_file]
+            .iter()
+            .enumerate()
+            // A gap starts at an empty block that doesn't follow another empty block.
+            .filter(|(i, block)| block.is_none() && (*i == 0 || disk_map[i - 1].is_some()))
+            .count()
     }
 }
 
 fn main() -> Result<()> {
-    let input = std::fs::read_to_string("input.txt")?;
+    let input = read_input(std::env::args().nth(1))?;
 
     // Part 1
     let part_1 = solve_part_1(&input)?;
@@ -396,6 +499,46 @@ mod tests {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn test_round_trip_parsed() {
+        let parsed = create_raw_disk_map().parse().unwrap();
+        let expected = create_parsed_disk_map();
+        let actual = parsed.expand().to_parsed();
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_from_blocks() {
+        let expected = create_expanded_disk_map();
+        let actual = DiskMap::<Expanded>::from_blocks(expected.state.0.clone());
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_blocks() {
+        let expanded = create_expanded_disk_map();
+        let compressed = create_compressed_disk_map();
+        let direct = expanded
+            .state
+            .0
+            .iter()
+            .filter(|block| block.is_some())
+            .count();
+
+        assert_eq!(expanded.blocks().filter(Option::is_some).count(), direct);
+        assert_eq!(direct, 28);
+        // Compressing moves blocks around but never loses any
+        assert_eq!(compressed.blocks().flatten().count(), direct);
+        assert_eq!(compressed.blocks().count(), expanded.blocks().count());
+        assert_eq!(expanded.blocks().next(), Some(Some(0)));
+        assert_eq!(compressed.blocks().collect::<Vec<_>>(), compressed.state.0);
+        // The last file is moved into the first gap, the tail is left empty
+        assert_eq!(compressed.blocks().nth(2), Some(Some(9)));
+        assert_eq!(compressed.blocks().last(), Some(None));
+    }
+
     #[test]
     fn test_compress_disk_map() {
         let expected = create_compressed_disk_map();
@@ -412,6 +555,58 @@ mod tests {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn test_checksum_filtered() {
+        let disk_map = create_compressed_disk_map();
+        let even = disk_map.checksum_filtered(|id| id % 2 == 0);
+        let odd = disk_map.checksum_filtered(|id| id % 2 == 1);
+
+        assert_eq!(even, 978);
+        assert_eq!(odd, 950);
+        assert_eq!(even + odd, disk_map.checksum());
+    }
+
+    #[test]
+    fn test_to_compact_string() {
+        // No gaps, so the files stay in id order and survive the round trip
+        let full = DiskMap {
+            state: Compressed(vec![Some(0), Some(0), Some(1), Some(1), Some(1), Some(2)]),
+        };
+        let compact = full.to_compact_string().unwrap();
+        assert_eq!(compact, "20301");
+        let round_trip = DiskMap::new(compact).parse().unwrap().expand().compress();
+        assert_eq!(round_trip, full);
+
+        // Trailing free space is kept as the last gap
+        let trailing = DiskMap {
+            state: Compressed(vec![Some(0), Some(1), None, None]),
+        };
+        assert_eq!(trailing.to_compact_string().unwrap(), "1012");
+
+        let too_long = DiskMap {
+            state: Compressed(vec![Some(0); 10]),
+        };
+        assert_eq!(too_long.to_compact_string(), None);
+        // The sample ends with 14 free blocks after compressing
+        assert_eq!(create_compressed_disk_map().to_compact_string(), None);
+    }
+
+    #[test]
+    fn test_gap_runs() {
+        // 00992111777.44.333....5555.6666.....8888..
+        let expected = vec![(11, 1), (14, 1), (18, 4), (26, 1), (31, 5), (40, 2)];
+        assert_eq!(create_compressed_contiguous_disk_map().gap_runs(), expected);
+
+        // Every free block ends up in one run at the end
+        assert_eq!(create_compressed_disk_map().gap_runs(), vec![(28, 14)]);
+    }
+
+    #[test]
+    fn test_fragmentation() {
+        assert_eq!(create_compressed_disk_map().fragmentation(), 0);
+        assert_eq!(create_compressed_contiguous_disk_map().fragmentation(), 5);
+    }
+
     #[test]
     fn test_solve_part_1() {
         let expected = 1928;
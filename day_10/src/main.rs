@@ -1,36 +1,25 @@
 use std::collections::{hash_map::Entry, HashMap, HashSet, VecDeque};
 
 use anyhow::Result;
+use common::read_input;
+use grid::Point;
+use rayon::prelude::*;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
-enum MapError {
+pub enum MapError {
     #[error("Tried to access an OoB position {0}")]
     OutOfBounds(Coordinate),
     #[error("Tried to access a position with no origin {0}")]
     MissingOrigin(Coordinate),
     #[error("Tried to access a position which was missing {0}")]
     MissingPosition(Coordinate),
+    #[error("The map is empty")]
+    EmptyMap,
 }
 
 /// 2d coordinate.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-struct Coordinate {
-    x: usize,
-    y: usize,
-}
-
-impl Coordinate {
-    fn new(x: usize, y: usize) -> Self {
-        Coordinate { x, y }
-    }
-}
-
-impl std::fmt::Display for Coordinate {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "({}, {})", self.x, self.y)
-    }
-}
+type Coordinate = Point<usize>;
 
 /// Node representation for the graph.
 #[derive(Debug, Default, PartialEq, Eq)]
@@ -39,21 +28,33 @@ struct Node {
     origins: HashSet<Coordinate>,
 }
 
+/// How the edges of a map behave.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Topology {
+    /// The edges are the end of the map.
+    #[default]
+    Bounded,
+    /// The edges wrap around to the opposite side.
+    Toroidal,
+}
+
 /// 2d representation of a map of integers.
 #[derive(Debug, PartialEq, Eq)]
-struct Map {
+pub struct Map {
     /// Width of the map.
     width: usize,
     /// Height of the map.
     height: usize,
     /// Inner representation of the map.
     inner: Vec<Vec<usize>>,
+    /// How the edges of the map behave.
+    topology: Topology,
 }
 
 impl Map {
     /// Get the integer value at a given position.
     fn get(&self, pos: Coordinate) -> Result<usize, MapError> {
-        if pos.x < self.width && pos.y < self.height {
+        if pos.in_bounds(self.width, self.height) {
             Ok(self.inner[pos.y][pos.x])
         } else {
             Err(MapError::OutOfBounds(pos))
@@ -75,23 +76,40 @@ impl Map {
         trailheads
     }
 
+    /// Get all trailheads in reading order, sorted by (y, x).
+    /// Unlike `get_trailheads`, the order is the same on every run.
+    pub fn trailheads_sorted(&self) -> Vec<Coordinate> {
+        let mut trailheads = self.get_trailheads().into_iter().collect::<Vec<_>>();
+        trailheads.sort_by_key(|c| (c.y, c.x));
+        trailheads
+    }
+
     /// Get all neighbours of a given position.
     /// Returns an array of 4 options, where None represents an OoB position.
+    /// Toroidal maps wrap to the opposite side, so they never have OoB neighbours.
+    /// On toroidal maps only 1 or 2 wide (or high), both sides wrap to the same cell, so it is only returned once.
     fn get_neighbours(&self, pos: Coordinate) -> [Option<Coordinate>; 4] {
-        let within_bounds = |x, y| {
-            if x < self.width && y < self.height {
-                Some(Coordinate::new(x, y))
-            } else {
-                None
+        match self.topology {
+            Topology::Bounded => pos
+                .neighbours()
+                .map(|n| n.filter(|n| n.in_bounds(self.width, self.height))),
+            Topology::Toroidal => {
+                let (w, h) = (self.width, self.height);
+                let mut neighbours = [
+                    Some(Coordinate::new(pos.x, (pos.y + h - 1) % h)),
+                    Some(Coordinate::new(pos.x, (pos.y + 1) % h)),
+                    Some(Coordinate::new((pos.x + w - 1) % w, pos.y)),
+                    Some(Coordinate::new((pos.x + 1) % w, pos.y)),
+                ];
+                // Drop any wrap back onto the position itself, or onto an earlier neighbour.
+                for i in 0..neighbours.len() {
+                    if neighbours[i] == Some(pos) || neighbours[..i].contains(&neighbours[i]) {
+                        neighbours[i] = None;
+                    }
+                }
+                neighbours
             }
-        };
-
-        [
-            pos.y.checked_sub(1).map(|y| Coordinate::new(pos.x, y)), // North
-            within_bounds(pos.x, pos.y + 1),                         // South
-            pos.x.checked_sub(1).map(|x| Coordinate::new(x, pos.y)), // West
-            within_bounds(pos.x + 1, pos.y),                         // East
-        ]
+        }
     }
 
     /// Count the number of reachables trails from all trailheads.
@@ -104,6 +122,66 @@ impl Map {
         counter
     }
 
+    /// The sum of every trailhead's score, the number of distinct summits it can reach.
+    /// Same as `count_trails`, named after the puzzle.
+    pub fn score(&self) -> usize {
+        self.count_trails()
+    }
+
+    /// The sum of every trailhead's rating, the number of distinct trails leading from it to any summit.
+    /// Same as `count_all_valid_trails`, named after the puzzle.
+    pub fn rating(&self) -> Result<usize, MapError> {
+        self.count_all_valid_trails()
+    }
+
+    /// The score of a single trailhead, the number of distinct summits it can reach.
+    pub fn score_of(&self, trailhead: Coordinate) -> Result<usize, MapError> {
+        self.get(trailhead)?;
+        Ok(self.count_valid_trails_from_trailhead(trailhead))
+    }
+
+    /// The rating of a single trailhead, the number of distinct trails leading from it to any summit.
+    /// Unlike `count_all_valid_trails` nothing is shared between trailheads, so this counts with its own memoized DFS.
+    pub fn rating_of(&self, trailhead: Coordinate) -> Result<usize, MapError> {
+        /// Count the trails from the position to any summit, caching the count for every position visited.
+        fn trails_from(
+            map: &Map,
+            pos: Coordinate,
+            memo: &mut HashMap<Coordinate, usize>,
+        ) -> Result<usize, MapError> {
+            if let Some(&count) = memo.get(&pos) {
+                return Ok(count);
+            }
+
+            let value = map.get(pos)?;
+            let count = if value == 9 {
+                1
+            } else {
+                let mut count = 0;
+                for neighbour in map.get_neighbours(pos).into_iter().flatten() {
+                    if valid_neighbours(value, map.get(neighbour)?) {
+                        count += trails_from(map, neighbour, memo)?;
+                    }
+                }
+                count
+            };
+
+            memo.insert(pos, count);
+            Ok(count)
+        }
+
+        trails_from(self, trailhead, &mut HashMap::new())
+    }
+
+    /// Same as `count_trails`, but the trailheads are searched in parallel.
+    /// Each search only borrows the map immutably, so they can all share it.
+    pub fn count_trails_par(&self) -> usize {
+        self.get_trailheads()
+            .par_iter()
+            .map(|&trailhead| self.count_valid_trails_from_trailhead(trailhead))
+            .sum()
+    }
+
     /// Count the number of valid trails from a given trailhead.
     /// Returns the number of valid trails.
     /// Uses a BFS approach.
@@ -236,7 +314,7 @@ impl Map {
             // We are safe to assume that the origin is in the explored map.
             let origin_path_count = explored
                 .get(&trailhead)
-                .ok_or_else(|| MapError::MissingPosition(trailhead))?
+                .ok_or(MapError::MissingPosition(trailhead))?
                 .paths;
 
             // Add the path count to the total counter.
@@ -245,6 +323,46 @@ impl Map {
 
         Ok(counter)
     }
+
+    /// Finds one concrete trail from the given trailhead up to an endpoint.
+    /// Returns None if the position isn't a trailhead or no trail can be completed.
+    /// Uses a DFS approach.
+    pub fn sample_trail(&self, trailhead: Coordinate) -> Option<Vec<Coordinate>> {
+        if self.get(trailhead).ok()? != 0 {
+            return None;
+        }
+
+        let mut trail = vec![trailhead];
+        // Positions which are known to not lead to an endpoint.
+        let mut dead_ends = HashSet::new();
+
+        while let Some(&current) = trail.last() {
+            let current_value = self.inner[current.y][current.x];
+            if current_value == 9 {
+                return Some(trail);
+            }
+
+            // Take the first neighbour which continues the trail.
+            let next = self
+                .get_neighbours(current)
+                .into_iter()
+                .flatten()
+                .find(|n| {
+                    !dead_ends.contains(n) && valid_neighbours(current_value, self.inner[n.y][n.x])
+                });
+
+            match next {
+                Some(next) => trail.push(next),
+                None => {
+                    // Backtrack and never try this position again.
+                    dead_ends.insert(current);
+                    trail.pop();
+                }
+            }
+        }
+
+        None
+    }
 }
 
 /// Checks if two values are valid neighbours.
@@ -272,7 +390,7 @@ fn bubble_counter(
     while let Some(current) = queue.pop_front() {
         let node = graph
             .get_mut(&current)
-            .ok_or_else(|| MapError::MissingOrigin(current))?;
+            .ok_or(MapError::MissingOrigin(current))?;
 
         node.paths += value;
 
@@ -296,17 +414,22 @@ fn parse_input(input: &str) -> Result<Map> {
         })
         .collect::<Result<Vec<Vec<_>>, _>>()?;
     let height = inner.len();
-    let width = inner[0].len();
+    // Toroidal maps wrap with the width and height, so they can't be zero.
+    let width = match inner.first() {
+        Some(row) if !row.is_empty() => row.len(),
+        _ => return Err(MapError::EmptyMap.into()),
+    };
 
     Ok(Map {
         width,
         height,
         inner,
+        topology: Topology::Bounded,
     })
 }
 
 fn main() -> Result<()> {
-    let input = std::fs::read_to_string("input.txt")?;
+    let input = read_input(std::env::args().nth(1))?;
     let map = parse_input(&input)?;
 
     // Part 1
@@ -349,6 +472,7 @@ mod test {
                 vec![8, 7, 6, 5],
                 vec![9, 8, 7, 6],
             ],
+            topology: Topology::Bounded,
         }
     }
 
@@ -366,6 +490,7 @@ mod test {
                 vec![0, 1, 3, 2, 9, 8, 0, 1],
                 vec![1, 0, 4, 5, 6, 7, 3, 2],
             ],
+            topology: Topology::Bounded,
         }
     }
 
@@ -391,6 +516,119 @@ mod test {
         assert_eq!(expected, actual);
     }
 
+    /// A single column where the only trail climbs off the top edge and continues from the bottom.
+    fn create_wrapping_test_map(topology: Topology) -> Map {
+        Map {
+            height: 10,
+            width: 1,
+            inner: vec![
+                vec![5],
+                vec![4],
+                vec![3],
+                vec![2],
+                vec![1],
+                vec![0],
+                vec![9],
+                vec![8],
+                vec![7],
+                vec![6],
+            ],
+            topology,
+        }
+    }
+
+    #[test]
+    fn test_get_neighbours_toroidal() {
+        let map = Map {
+            topology: Topology::Toroidal,
+            ..create_test_map()
+        };
+        let expected = [
+            Some(Coordinate::new(0, 3)),
+            Some(Coordinate::new(0, 1)),
+            Some(Coordinate::new(3, 0)),
+            Some(Coordinate::new(1, 0)),
+        ];
+        let actual = map.get_neighbours(Coordinate::new(0, 0));
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_toroidal_narrow_map() {
+        // Snakes left and right, every horizontal step wraps onto the same cell both ways
+        let map = Map {
+            width: 2,
+            height: 5,
+            inner: vec![vec![0, 1], vec![3, 2], vec![4, 5], vec![7, 6], vec![8, 9]],
+            topology: Topology::Toroidal,
+        };
+
+        assert_eq!(
+            map.get_neighbours(Coordinate::new(0, 0)),
+            [
+                Some(Coordinate::new(0, 4)),
+                Some(Coordinate::new(0, 1)),
+                Some(Coordinate::new(1, 0)),
+                None,
+            ]
+        );
+        assert_eq!(map.count_trails(), 1);
+        assert_eq!(map.count_all_valid_trails().unwrap(), 1);
+        assert_eq!(map.rating_of(Coordinate::new(0, 0)).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_parse_empty_map() {
+        assert!(parse_input("").is_err());
+        assert!(parse_input("  \n  ").is_err());
+    }
+
+    #[test]
+    fn test_toroidal_trail() {
+        let bounded = create_wrapping_test_map(Topology::Bounded);
+        let toroidal = create_wrapping_test_map(Topology::Toroidal);
+
+        assert_eq!(bounded.count_trails(), 0);
+        assert_eq!(toroidal.count_trails(), 1);
+        assert_eq!(toroidal.count_all_valid_trails().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_trailheads_sorted() {
+        let map = create_large_test_map();
+        let expected = vec![
+            Coordinate::new(2, 0),
+            Coordinate::new(4, 0),
+            Coordinate::new(4, 2),
+            Coordinate::new(6, 4),
+            Coordinate::new(2, 5),
+            Coordinate::new(5, 5),
+            Coordinate::new(0, 6),
+            Coordinate::new(6, 6),
+            Coordinate::new(1, 7),
+        ];
+        let actual = map.trailheads_sorted();
+
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_sample_trail() {
+        let map = create_test_map();
+        let trail = map.sample_trail(Coordinate::new(0, 0)).unwrap();
+        let values = trail
+            .iter()
+            .map(|&p| map.get(p).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(values, (0..=9).collect::<Vec<_>>());
+        assert!(trail
+            .windows(2)
+            .all(|w| w[0].manhattan_distance(&w[1]) == 1));
+        assert_eq!(map.sample_trail(Coordinate::new(1, 0)), None);
+    }
+
     #[test]
     fn test_count_valid_trails_from_trailhead() {
         let map = create_large_test_map();
@@ -410,6 +648,48 @@ mod test {
         assert_eq!(expected, actual);
     }
 
+    #[test]
+    fn test_count_trails_par() {
+        let map = create_large_test_map();
+        assert_eq!(map.count_trails_par(), map.count_trails());
+        assert_eq!(map.count_trails_par(), 36);
+
+        let map = create_test_map();
+        assert_eq!(map.count_trails_par(), map.count_trails());
+    }
+
+    #[test]
+    fn test_score_and_rating() {
+        let map = create_large_test_map();
+        let trailheads = map.trailheads_sorted();
+
+        let scores = trailheads
+            .iter()
+            .map(|&t| map.score_of(t).unwrap())
+            .collect::<Vec<_>>();
+        let ratings = trailheads
+            .iter()
+            .map(|&t| map.rating_of(t).unwrap())
+            .collect::<Vec<_>>();
+
+        // Per trailhead values from the puzzle description, in reading order
+        assert_eq!(scores, vec![5, 6, 5, 3, 1, 3, 5, 3, 5]);
+        assert_eq!(ratings, vec![20, 24, 10, 4, 1, 4, 5, 8, 5]);
+        assert_eq!(scores.iter().sum::<usize>(), map.score());
+        assert_eq!(map.score(), 36);
+        assert_eq!(ratings.iter().sum::<usize>(), map.rating().unwrap());
+        assert_eq!(map.rating().unwrap(), 81);
+    }
+
+    #[test]
+    fn test_score_and_rating_out_of_bounds() {
+        let map = create_test_map();
+        let pos = Coordinate::new(map.width, 0);
+
+        assert!(matches!(map.score_of(pos), Err(MapError::OutOfBounds(p)) if p == pos));
+        assert!(matches!(map.rating_of(pos), Err(MapError::OutOfBounds(p)) if p == pos));
+    }
+
     #[test]
     fn test_solve_part_2_small() {
         let map = create_test_map();
@@ -1,6 +1,14 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, fmt, str::FromStr};
 
 use anyhow::Result;
+use common::read_input;
+use thiserror::Error;
+
+#[derive(Debug, Error, PartialEq)]
+enum StoneParseError {
+    #[error("Invalid number: {token:?}")]
+    InvalidNumber { token: String },
+}
 
 #[derive(Debug, PartialEq, Clone)]
 struct Stones(Vec<usize>);
@@ -9,17 +17,8 @@ impl Stones {
     fn engrave(&mut self) -> &mut Self {
         let mut result = Vec::new();
 
-        for stone in self.0.iter() {
-            let rule = Rule::find(*stone);
-            match rule {
-                Rule::Flip => result.push(1),
-                Rule::Split => {
-                    let (a, b) = split_integer(*stone);
-                    result.push(a);
-                    result.push(b);
-                }
-                Rule::Multiply => result.push(*stone * 2024),
-            }
+        for &stone in self.0.iter() {
+            result.extend(Rule::find(stone).apply(stone));
         }
 
         self.0 = result;
@@ -38,6 +37,43 @@ impl Stones {
     }
 }
 
+impl FromStr for Stones {
+    type Err = StoneParseError;
+
+    /// Parses whitespace separated stones, e.g. "125 17".
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_stones(s)
+    }
+}
+
+/// Parses whitespace separated stones, naming the first token that isn't a valid number.
+fn parse_stones(input: &str) -> Result<Stones, StoneParseError> {
+    let stones = input
+        .split_ascii_whitespace()
+        .map(|token| {
+            token
+                .parse::<usize>()
+                .map_err(|_| StoneParseError::InvalidNumber {
+                    token: token.to_string(),
+                })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Stones(stones))
+}
+
+impl fmt::Display for Stones {
+    /// Prints the stones separated by single spaces.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, stone) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", stone)?;
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, PartialEq)]
 enum Rule {
     Flip,
@@ -49,26 +85,43 @@ impl Rule {
     fn find(n: usize) -> Rule {
         if n == 0 {
             Rule::Flip
-        } else if count_digits(n) % 2 == 0 {
+        } else if count_digits(n).is_multiple_of(2) {
             Rule::Split
         } else {
             Rule::Multiply
         }
     }
+
+    /// Produces the stones that replace the given one, in order.
+    /// Assumes the rule was found for that stone, splitting an odd number of digits panics in debug builds.
+    fn apply(&self, stone: usize) -> Vec<usize> {
+        match self {
+            Rule::Flip => vec![1],
+            Rule::Split => {
+                let (a, b) = split_integer(stone);
+                vec![a, b]
+            }
+            Rule::Multiply => vec![stone * 2024],
+        }
+    }
 }
 
-/// Assumes that the input is a positive integer
-/// and has an even number of digits.
+/// Assumes that the input has an even number of digits.
 fn split_integer(n: usize) -> (usize, usize) {
-    let half_digits = count_digits(n) / 2;
+    let digits = count_digits(n);
+    debug_assert!(
+        digits.is_multiple_of(2),
+        "split_integer called with an odd number of digits: {n}"
+    );
+    let half_digits = digits / 2;
     let divisor = 10usize.pow(half_digits as u32);
     (n / divisor, n % divisor)
 }
 
-/// Counts the number of digits in a positive integer.
+/// Counts the number of digits in an integer, zero having a single digit.
 fn count_digits(n: usize) -> usize {
-    let mut counter = 0;
-    let mut num = n;
+    let mut counter = 1;
+    let mut num = n / 10;
     while num > 0 {
         num /= 10;
         counter += 1;
@@ -77,14 +130,8 @@ fn count_digits(n: usize) -> usize {
 }
 
 fn main() -> Result<()> {
-    let input = std::fs::read_to_string("input.txt")?;
-    let stones = Stones(
-        input
-            .trim()
-            .split_ascii_whitespace()
-            .map(|s| s.parse::<usize>())
-            .collect::<Result<Vec<_>, _>>()?,
-    );
+    let input = read_input(std::env::args().nth(1))?;
+    let stones = parse_stones(&input)?;
 
     // Part 1
     let part_1 = solve_part_1(stones.clone());
@@ -102,48 +149,69 @@ fn solve_part_1(mut stones: Stones) -> usize {
     stones.count_stones()
 }
 
-/// This function is pretty much a 1 to 1 copy of the solution from the following
+/// Originally a 1 to 1 copy of the HashMap folding solution from the following
 /// YouTube video: https://www.youtube.com/watch?v=La6OcNBUjVo
-/// I had no idea how to solve the second part of the problem, so I looked up a solution.
+/// Now just the total of the final histogram.
 fn solve_part_2(stones: Stones) -> usize {
-    // Cast the stones into a HashMap since the order does not actually matter.
-    let mut current = stones
-        .0
-        .iter()
-        .map(|&x| (x, 1))
-        .collect::<HashMap<usize, usize>>();
-
-    // Engrave the stones 75 times.
-    for _ in 0..75 {
-        // Create a new HashMap to store the next iteration of stones.
+    histogram_after(&stones, 75).values().sum()
+}
+
+/// Returns how many stones of each value there are after blinking n times.
+/// Order doesn't matter for the count, so every stone with the same value is handled once per blink.
+fn histogram_after(stones: &Stones, blinks: usize) -> HashMap<usize, usize> {
+    let mut histogram = HashMap::new();
+    for &stone in stones.0.iter() {
+        *histogram.entry(stone).or_insert(0) += 1;
+    }
+
+    for _ in 0..blinks {
         let mut next = HashMap::new();
-        // Iterate over the current stones.
-        for (stone, count) in current {
-            // Split the stone into either 1 or 2 new stones, depending on the rule.
+        for (stone, count) in histogram {
             for new_stone in split_stone(stone) {
-                // Insert the new stone into the next HashMap.
-                let entry = next.entry(new_stone).or_default();
-                // Add the count of the current stone to the new stone.
-                *entry += count;
+                *next.entry(new_stone).or_insert(0) += count;
             }
         }
+        histogram = next;
+    }
+
+    histogram
+}
 
-        // Set the current HashMap to the next HashMap.
-        current = next;
+/// Counts how many stones a single stone becomes after the given number of blinks.
+/// Results are memoized by (stone, blinks) in the provided cache.
+pub fn count_after_blinks(
+    stone: usize,
+    blinks: usize,
+    cache: &mut HashMap<(usize, usize), usize>,
+) -> usize {
+    if blinks == 0 {
+        return 1;
     }
 
-    current.values().sum()
+    if let Some(&count) = cache.get(&(stone, blinks)) {
+        return count;
+    }
+
+    let count = split_stone(stone)
+        .into_iter()
+        .map(|new_stone| count_after_blinks(new_stone, blinks - 1, cache))
+        .sum();
+
+    cache.insert((stone, blinks), count);
+    count
+}
+
+/// Returns the ordered stones after blinking n times, without mutating the input.
+/// The number of stones grows exponentially, so this is only viable for small n;
+/// use count_after_blinks when only the count is needed.
+pub fn blink(stones: &[usize], n: usize) -> Vec<usize> {
+    (0..n).fold(stones.to_vec(), |current, _| {
+        current.into_iter().flat_map(split_stone).collect()
+    })
 }
 
 fn split_stone(stone: usize) -> Vec<usize> {
-    if stone == 0 {
-        vec![1]
-    } else if count_digits(stone) % 2 == 0 {
-        let (a, b) = split_integer(stone);
-        vec![a, b]
-    } else {
-        vec![stone * 2024]
-    }
+    Rule::find(stone).apply(stone)
 }
 
 #[cfg(test)]
@@ -163,6 +231,53 @@ mod tests {
         assert_eq!(count_digits(1234), 4);
         assert_eq!(count_digits(123456), 6);
         assert_eq!(count_digits(12345678), 8);
+        assert_eq!(count_digits(0), 1);
+        assert_eq!(count_digits(7), 1);
+    }
+
+    // The guard is a debug assertion, so release builds don't panic
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn test_split_integer_odd_digits() {
+        split_integer(123);
+    }
+
+    #[test]
+    fn test_split_stone_zero() {
+        // Zero has a single digit, so it must flip rather than reach split_integer
+        assert_eq!(split_stone(0), vec![1]);
+    }
+
+    #[test]
+    fn test_parse_and_display_stones() {
+        let stones = "125 17".parse::<Stones>().unwrap();
+        assert_eq!(stones, Stones(vec![125, 17]));
+        assert_eq!(stones.to_string(), "125 17");
+
+        // Surrounding and repeated whitespace is ignored
+        assert_eq!(" 0  1\n".parse::<Stones>().unwrap().to_string(), "0 1");
+        assert_eq!("".parse::<Stones>().unwrap(), Stones(vec![]));
+        assert!("12 x".parse::<Stones>().is_err());
+    }
+
+    #[test]
+    fn test_parse_stones_invalid() {
+        let err = parse_stones("125 abc 17").unwrap_err();
+        assert_eq!(
+            err,
+            StoneParseError::InvalidNumber {
+                token: "abc".to_string()
+            }
+        );
+        assert!(err.to_string().contains("\"abc\""));
+
+        // Negative numbers aren't valid stones either
+        assert!(matches!(
+            parse_stones("1 -2"),
+            Err(StoneParseError::InvalidNumber { token }) if token == "-2"
+        ));
+        assert_eq!(parse_stones("125 17"), Ok(Stones(vec![125, 17])));
     }
 
     #[test]
@@ -215,4 +330,60 @@ mod tests {
         assert_eq!(Rule::find(1), Rule::Multiply);
         assert_eq!(Rule::find(10), Rule::Split);
     }
+
+    #[test]
+    fn test_apply_rules() {
+        assert_eq!(Rule::find(0).apply(0), vec![1]);
+        assert_eq!(Rule::find(1000).apply(1000), vec![10, 0]);
+        assert_eq!(Rule::find(17).apply(17), vec![1, 7]);
+        assert_eq!(Rule::find(1).apply(1), vec![2024]);
+        assert_eq!(Rule::find(125).apply(125), vec![253000]);
+
+        // Both blinking paths agree
+        for stone in [0, 1, 10, 99, 999, 2024] {
+            assert_eq!(split_stone(stone), Rule::find(stone).apply(stone));
+        }
+    }
+
+    #[test]
+    fn test_count_after_blinks() {
+        let mut cache = HashMap::new();
+        assert_eq!(count_after_blinks(0, 1, &mut cache), 1);
+
+        let total: usize = [125, 17]
+            .iter()
+            .map(|&stone| count_after_blinks(stone, 25, &mut cache))
+            .sum();
+        assert_eq!(total, 55312);
+    }
+
+    #[test]
+    fn test_histogram_after() {
+        let stones = Stones(vec![125, 17]);
+
+        for blinks in 0..=6 {
+            let mut expected = HashMap::new();
+            for stone in blink(&stones.0, blinks) {
+                *expected.entry(stone).or_insert(0) += 1;
+            }
+            assert_eq!(histogram_after(&stones, blinks), expected);
+        }
+
+        // After 6 blinks there are four 2s and a pair each of 0, 6, 40 and 48
+        let histogram = histogram_after(&stones, 6);
+        assert_eq!(histogram[&2], 4);
+        assert_eq!(histogram[&40], 2);
+        assert_eq!(histogram.get(&1), None);
+        assert_eq!(histogram_after(&stones, 25).values().sum::<usize>(), 55312);
+    }
+
+    #[test]
+    fn test_blink() {
+        let expected = vec![
+            2097446912, 14168, 4048, 2, 0, 2, 4, 40, 48, 2024, 40, 48, 80, 96, 2, 8, 6, 7, 6, 0, 3,
+            2,
+        ];
+        assert_eq!(blink(&[125, 17], 6), expected);
+        assert_eq!(blink(&[125, 17], 0), vec![125, 17]);
+    }
 }
@@ -4,65 +4,46 @@ use std::{
 };
 
 use anyhow::Result;
+use common::read_input;
+use grid::Point;
 use thiserror::Error;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct Region {
     area: usize,
     perimeter: usize,
+    cells: HashSet<Coordinate>,
 }
 
 impl Region {
+    /// Creates a region without any cells, only useful for pricing.
     pub fn new(area: usize, perimeter: usize) -> Self {
-        Region { area, perimeter }
+        Region {
+            area,
+            perimeter,
+            cells: HashSet::new(),
+        }
     }
     fn price(&self) -> usize {
         self.area * self.perimeter
     }
-}
-
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, PartialOrd, Ord)]
-pub struct Coordinate {
-    x: i32,
-    y: i32,
-}
-
-impl Coordinate {
-    fn new(x: i32, y: i32) -> Self {
-        Coordinate { x, y }
+    /// Returns the cells of the region sorted by (y, x), so the order is the same on every run.
+    pub fn cells_sorted(&self) -> Vec<Coordinate> {
+        let mut cells = self.cells.iter().copied().collect::<Vec<_>>();
+        cells.sort_by_key(|c| (c.y, c.x));
+        cells
     }
+}
 
-    fn in_bounds(&self, width: i32, height: i32) -> bool {
-        self.x >= 0 && self.x < width && self.y >= 0 && self.y < height
-    }
-
-    fn neighbours_bounded(&self, width: i32, height: i32) -> Vec<Coordinate> {
-        let mut neighbours = Vec::new();
-        let directions = [(0, 1), (1, 0), (0, -1), (-1, 0)];
-
-        for (dx, dy) in directions.iter() {
-            let neighbour = Coordinate::new(self.x + dx, self.y + dy);
-            if neighbour.in_bounds(width, height) {
-                neighbours.push(neighbour);
-            }
-        }
-
-        neighbours
-    }
+pub type Coordinate = Point<i32>;
 
-    /// All neighbours of a coordinate, including ordinals.
-    fn all_neighbours(&self) -> [Coordinate; 8] {
-        [
-            Coordinate::new(self.x - 1, self.y - 1), // NW
-            Coordinate::new(self.x, self.y - 1),     // N
-            Coordinate::new(self.x + 1, self.y - 1), // NE
-            Coordinate::new(self.x - 1, self.y),     // W
-            Coordinate::new(self.x + 1, self.y),     // E
-            Coordinate::new(self.x - 1, self.y + 1), // SW
-            Coordinate::new(self.x, self.y + 1),     // S
-            Coordinate::new(self.x + 1, self.y + 1), // SE
-        ]
-    }
+/// Which cells count as touching when growing a region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Connectivity {
+    /// Only cells sharing an edge, as in the puzzle.
+    Four,
+    /// Cells touching at a corner join the region too.
+    Eight,
 }
 
 pub struct Graph {
@@ -70,8 +51,12 @@ pub struct Graph {
 }
 
 impl Graph {
-    fn find_regions(&self) -> Regions {
+    /// Split the graph into regions of matching tokens, joined according to the connectivity.
+    /// Perimeters and sides only ever count shared edges, so with `Connectivity::Eight` two cells
+    /// touching at a corner still contribute all of their own edges.
+    fn find_regions(&self, connectivity: Connectivity) -> Regions {
         let mut regions = Vec::new();
+        let (width, height) = self.size();
         // HashSet of all the coordinates which have been completly handled.
         let mut completed = HashSet::<Coordinate>::new();
 
@@ -90,44 +75,48 @@ impl Graph {
             // Loop while there are any unexplored neighbours
             while let Some(current) = queue.pop_front() {
                 // Skip any nodes that we have already examined and found to be part of the region.
-                if explored.contains(&current) {
+                if !explored.insert(current) {
                     continue;
                 }
-                // Get the node, we can assume it exists.
-                let node = self
-                    .nodes
-                    .get(&current)
-                    .expect("Expected node to be present.");
-                // Check if the current token matches the one we are looking for.
-                if node.token == token {
-                    // If it is, then add it to the explored set and queue up its neighbours.
-                    explored.insert(current);
-
-                    // If the node is on the edge of the graph, then we can simulate a border with the outside
-                    // by calculating the number of out of bounds connections and adding that to the perimeter.
-                    // println!("{:?}", node.connections.len());
-                    let oob_connections = 4 - node.connections.len();
-                    perimeter += oob_connections;
-
-                    for connection in &node.connections {
-                        queue.push_back(*connection);
+                let neighbours = self.neighbour_tokens(current);
+
+                // If the node is on the edge of the graph, then we can simulate a border with the outside
+                // by calculating the number of out of bounds connections and adding that to the perimeter.
+                perimeter += 4 - neighbours.len();
+
+                for (neighbour, neighbour_token) in neighbours {
+                    if neighbour_token == token {
+                        // Same token, so it is part of the region.
+                        queue.push_back(neighbour);
+                    } else {
+                        // If it is a different token, then we can extend the perimeter by 1.
+                        perimeter += 1;
                     }
-                } else {
-                    // If it is a different token, then we can extend the perimeter by 1.
-                    perimeter += 1;
+                }
+
+                if connectivity == Connectivity::Eight {
+                    // Diagonals join the region but don't share an edge, so the perimeter is unaffected.
+                    // The orthogonal neighbours come round again, but are skipped once explored.
+                    queue.extend(
+                        current
+                            .neighbours8(width, height)
+                            .into_iter()
+                            .filter(|n| self.nodes.get(n).is_some_and(|node| node.token == token)),
+                    );
                 }
             }
 
+            // Update the completed Set with all the explored positions.
+            completed.extend(explored.iter().copied());
             // Build the new region
             let new_region = Region {
                 area: explored.len(),
                 perimeter,
+                cells: explored,
             };
 
             // Add the new region
-            regions.push((new_region, explored.clone()));
-            // Update the completed Set with all the explored positions.
-            completed.extend(explored);
+            regions.push(new_region);
         }
 
         Regions(regions)
@@ -136,6 +125,69 @@ impl Graph {
     pub fn new(nodes: HashMap<Coordinate, Node>) -> Self {
         Graph { nodes }
     }
+
+    /// The width and height of the smallest grid holding every node.
+    fn size(&self) -> (i32, i32) {
+        self.nodes
+            .keys()
+            .fold((0, 0), |(w, h), c| (w.max(c.x + 1), h.max(c.y + 1)))
+    }
+
+    /// Get the tokens of every region that is completely surrounded by a single other region.
+    /// Regions touching the edge of the grid are never enclosed. Sorted so the order is the same on every run.
+    pub fn enclosed_regions(&self) -> Vec<char> {
+        let regions = self.find_regions(Connectivity::Four);
+        // Map every coordinate to the index of the region that contains it.
+        let region_of = regions
+            .0
+            .iter()
+            .enumerate()
+            .flat_map(|(i, r)| r.cells.iter().map(move |c| (*c, i)))
+            .collect::<HashMap<Coordinate, usize>>();
+
+        let mut enclosed = Vec::new();
+        for (i, region) in regions.0.iter().enumerate() {
+            let mut adjacent = HashSet::new();
+            let mut touches_border = false;
+            for cell in &region.cells {
+                let neighbours = self.neighbour_tokens(*cell);
+                // Fewer than 4 neighbours means the cell is on the edge of the grid.
+                touches_border |= neighbours.len() < 4;
+                adjacent.extend(
+                    neighbours
+                        .iter()
+                        .map(|(n, _)| region_of[n])
+                        .filter(|&j| j != i),
+                );
+            }
+
+            if !touches_border && adjacent.len() == 1 {
+                // Every cell in a region shares a token, so any of them will do.
+                let cell = region
+                    .cells
+                    .iter()
+                    .next()
+                    .expect("Regions are never empty.");
+                enclosed.push(self.nodes[cell].token);
+            }
+        }
+
+        enclosed.sort();
+        enclosed
+    }
+
+    /// Get every in bounds neighbour of the given coordinate, along with its token.
+    /// Returns an empty Vec if the coordinate isn't in the graph.
+    fn neighbour_tokens(&self, c: Coordinate) -> Vec<(Coordinate, char)> {
+        let Some(node) = self.nodes.get(&c) else {
+            return Vec::new();
+        };
+
+        node.connections
+            .iter()
+            .filter_map(|n| self.nodes.get(n).map(|node| (*n, node.token)))
+            .collect()
+    }
 }
 
 #[derive(Debug, Error)]
@@ -188,18 +240,25 @@ impl Node {
     }
 }
 
-struct Regions(Vec<(Region, HashSet<Coordinate>)>);
+pub struct Regions(Vec<Region>);
 
 impl Regions {
     fn total_price(&self) -> usize {
-        self.0.iter().map(|(r, _)| r.price()).sum()
+        self.0.iter().map(|r| r.price()).sum()
     }
 
     fn total_discounted_price(&self) -> usize {
-        self.0
-            .iter()
-            .map(|(r, set)| r.area * count_sides(set))
-            .sum()
+        self.0.iter().map(|r| r.area * count_sides(&r.cells)).sum()
+    }
+
+    /// The region with the largest area, or None if there are no regions.
+    pub fn largest(&self) -> Option<&Region> {
+        self.0.iter().max_by_key(|r| r.area)
+    }
+
+    /// The region with the smallest area, or None if there are no regions.
+    pub fn smallest(&self) -> Option<&Region> {
+        self.0.iter().min_by_key(|r| r.area)
     }
 }
 
@@ -243,30 +302,33 @@ fn count_sides(coords: &HashSet<Coordinate>) -> usize {
 
 fn neighbourhood_grid(coord: Coordinate, set: &HashSet<Coordinate>) -> [bool; 9] {
     let neighbours = coord.all_neighbours();
+    let contains = |n: Option<Coordinate>| n.is_some_and(|n| set.contains(&n));
 
     [
-        set.contains(&neighbours[0]),
-        set.contains(&neighbours[1]),
-        set.contains(&neighbours[2]),
-        set.contains(&neighbours[3]),
+        contains(neighbours[0]),
+        contains(neighbours[1]),
+        contains(neighbours[2]),
+        contains(neighbours[3]),
         true,
-        set.contains(&neighbours[4]),
-        set.contains(&neighbours[5]),
-        set.contains(&neighbours[6]),
-        set.contains(&neighbours[7]),
+        contains(neighbours[4]),
+        contains(neighbours[5]),
+        contains(neighbours[6]),
+        contains(neighbours[7]),
     ]
 }
 
 fn main() -> Result<()> {
-    let input = std::fs::read_to_string("input.txt")?;
+    let input = read_input(std::env::args().nth(1))?;
     let graph = input.parse::<Graph>()?;
 
     // Part 1
-    let part_1 = graph.find_regions().total_price();
+    let part_1 = graph.find_regions(Connectivity::Four).total_price();
     println!("Part 1: {}", part_1);
 
     // Part 2
-    let part_2 = graph.find_regions().total_discounted_price();
+    let part_2 = graph
+        .find_regions(Connectivity::Four)
+        .total_discounted_price();
     println!("Part 2: {}", part_2);
 
     Ok(())
@@ -325,6 +387,37 @@ mod test {
         assert_eq!(graph.nodes.len(), 16);
     }
 
+    #[test]
+    fn test_neighbour_tokens() {
+        let graph = create_test_graph();
+        let mut actual = graph.neighbour_tokens(Coordinate::new(2, 1));
+        actual.sort();
+        let expected = vec![
+            (Coordinate::new(1, 1), 'B'),
+            (Coordinate::new(2, 0), 'A'),
+            (Coordinate::new(2, 2), 'C'),
+            (Coordinate::new(3, 1), 'D'),
+        ];
+
+        assert_eq!(actual, expected);
+        // Corners only have two neighbours
+        assert_eq!(graph.neighbour_tokens(Coordinate::new(0, 0)).len(), 2);
+        assert!(graph.neighbour_tokens(Coordinate::new(4, 4)).is_empty());
+    }
+
+    #[test]
+    fn test_enclosed_regions() {
+        let graph = "OOOOO\nOXXOO\nOOOOO\nZZZZZ".parse::<Graph>().unwrap();
+        assert_eq!(graph.enclosed_regions(), vec!['X']);
+
+        // Only touches two regions, and the edge of the grid
+        assert!(create_test_graph().enclosed_regions().is_empty());
+
+        // X and A are both inside the O, but each also touches the other
+        let graph = "OOOOO\nOXXAO\nOOOOO".parse::<Graph>().unwrap();
+        assert!(graph.enclosed_regions().is_empty());
+    }
+
     #[test]
     fn test_count_sides() {
         let coords = [
@@ -343,24 +436,84 @@ mod test {
     #[test]
     fn test_solve_part_2() {
         let graph = create_test_graph();
-        let regions = graph.find_regions();
+        let regions = graph.find_regions(Connectivity::Four);
         let expected = 80;
 
         assert_eq!(regions.total_discounted_price(), expected);
     }
 
+    #[test]
+    fn test_find_regions_connectivity() {
+        let graph = "ABB\nBAB\nBBA".parse::<Graph>().unwrap();
+
+        // Each A on the diagonal is on its own, and the Bs are split in two
+        let regions = graph.find_regions(Connectivity::Four);
+        assert_eq!(regions.0.len(), 5);
+        assert_eq!(regions.largest().unwrap().area, 3);
+
+        // Touching corners join up the As, and the Bs through (2, 1) and (1, 2)
+        let regions = graph.find_regions(Connectivity::Eight);
+        assert_eq!(regions.0.len(), 2);
+        let a = regions
+            .0
+            .iter()
+            .find(|r| r.cells.contains(&Coordinate::new(0, 0)))
+            .unwrap();
+        assert_eq!(a.area, 3);
+        // Perimeter and sides still only count edges
+        assert_eq!(a.perimeter, 12);
+        assert_eq!(count_sides(&a.cells), 12);
+        assert_eq!(regions.largest().unwrap().area, 6);
+
+        // No diagonals in the sample so both modes agree on the price
+        let graph = create_test_graph();
+        assert_eq!(
+            graph.find_regions(Connectivity::Eight).total_price(),
+            graph.find_regions(Connectivity::Four).total_price()
+        );
+    }
+
     #[test]
     fn test_find_regions() {
         let graph = create_test_graph();
-        let regions = graph.find_regions();
+        let regions = graph.find_regions(Connectivity::Four);
 
         assert_eq!(regions.0.len(), 5);
     }
 
+    #[test]
+    fn test_largest_and_smallest_regions() {
+        let graph = create_test_graph();
+        let regions = graph.find_regions(Connectivity::Four);
+
+        assert_eq!(regions.largest().map(|r| r.area), Some(4));
+        assert_eq!(regions.smallest().map(|r| r.area), Some(1));
+        assert!(Regions(vec![]).largest().is_none());
+    }
+
+    #[test]
+    fn test_cells_sorted() {
+        let graph = create_test_graph();
+        let regions = graph.find_regions(Connectivity::Four);
+        let c_region = regions
+            .0
+            .iter()
+            .find(|r| r.cells.contains(&Coordinate::new(2, 1)))
+            .unwrap();
+        let expected = vec![
+            Coordinate::new(2, 1),
+            Coordinate::new(2, 2),
+            Coordinate::new(3, 2),
+            Coordinate::new(3, 3),
+        ];
+
+        assert_eq!(c_region.cells_sorted(), expected);
+    }
+
     #[test]
     fn test_total_price() {
         let graph = create_test_graph();
-        let regions = graph.find_regions();
+        let regions = graph.find_regions(Connectivity::Four);
         let expected = 140;
         let actual = regions.total_price();
 
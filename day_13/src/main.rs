@@ -1,8 +1,11 @@
 use anyhow::Result;
+use common::read_input;
 use regex::Regex;
 use thiserror::Error;
 
 const TRILLION: i64 = 10_000_000_000_000;
+/// Token cost of pressing (A, B) once, as in the puzzle.
+const DEFAULT_COSTS: (i64, i64) = (3, 1);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct ClawConfig {
@@ -20,46 +23,72 @@ impl ClawConfig {
         }
     }
 
-    pub fn winnable(&self) -> bool {
-        let gcd_x = gcd(self.button_a.0, self.button_b.0);
-        let gcd_y = gcd(self.button_a.1, self.button_b.1);
+    /// Returns the button presses and token cost as `(a_presses, b_presses, token_cost)`,
+    /// or None if the prize can't be reached with a non-negative number of presses,
+    /// or the cost of reaching it doesn't fit in an i64.
+    pub fn solve(&self) -> Option<(i64, i64, i64)> {
+        self.solve_with_costs(DEFAULT_COSTS)
+    }
+
+    /// Same as `solve`, but with the token costs of the A and B buttons given as `(cost_a, cost_b)`.
+    pub fn solve_with_costs(&self, (cost_a, cost_b): (i64, i64)) -> Option<(i64, i64, i64)> {
+        let (a, b) = self.solve_bounded(None)?;
+
+        Some((a, b, self.price(a, b, cost_a, cost_b)?))
+    }
+
+    /// Token cost of pressing A `a` times and B `b` times, or None if it overflows an i64.
+    /// The solver can return press counts close to i64::MAX, so this can't be assumed to fit.
+    pub fn price(&self, a: i64, b: i64, cost_a: i64, cost_b: i64) -> Option<i64> {
+        a.checked_mul(cost_a)?.checked_add(b.checked_mul(cost_b)?)
+    }
 
-        if self.prize.0 % gcd_x != 0 || self.prize.1 % gcd_y != 0 {
-            false
-        } else {
-            true
+    /// Returns the button presses as `(a_presses, b_presses)`, or None if the prize can't be reached
+    /// with a non-negative number of presses of at most `max_presses` each. None means unbounded.
+    pub fn solve_bounded(&self, max_presses: Option<i64>) -> Option<(i64, i64)> {
+        let (a, b) = self.spider_hater_4_equation()?;
+        if a < 0 || b < 0 {
+            return None;
+        }
+        if max_presses.is_some_and(|max| a > max || b > max) {
+            return None;
         }
+
+        Some((a, b))
     }
 
     fn spider_hater_4_equation(&self) -> Option<(i64, i64)> {
         // Taken from this Reddit comment:
         // https://www.reddit.com/r/adventofcode/comments/1hd5b6o/comment/m1tx7yy/
         // `b=(py*ax-px*ay)/(by*ax-bx*ay) a=(px-b*bx)/ax`
-        let (px, py) = self.prize;
-        let (ax, ay) = self.button_a;
-        let (bx, by) = self.button_b;
+        // Worked out in i128, the products overflow i64 with trillion scale prizes and large deltas.
+        let (px, py) = (self.prize.0 as i128, self.prize.1 as i128);
+        let (ax, ay) = (self.button_a.0 as i128, self.button_a.1 as i128);
+        let (bx, by) = (self.button_b.0 as i128, self.button_b.1 as i128);
+
+        // Parallel buttons have no unique solution
+        let determinant = by * ax - bx * ay;
+        if determinant == 0 {
+            return None;
+        }
 
-        let b = (py * ax - px * ay) / (by * ax - bx * ay);
+        let b = (py * ax - px * ay) / determinant;
         // Check if the division is exact (no remainder)
-        if (py * ax - px * ay) % (by * ax - bx * ay) != 0 {
+        if (py * ax - px * ay) % determinant != 0 {
             return None;
         }
 
-        let a = (px - b * bx) / ax;
+        // With signed deltas button A may not move along X, so fall back to the Y axis
+        let (p, b_delta, a_delta) = if ax != 0 { (px, bx, ax) } else { (py, by, ay) };
+        let a = (p - b * b_delta) / a_delta;
         // Check if the division is exact (no remainder)
-        if (px - b * bx) % ax != 0 {
+        if (p - b * b_delta) % a_delta != 0 {
             return None;
         }
 
-        Some((a, b))
-    }
-}
-
-fn gcd(a: i64, b: i64) -> i64 {
-    if b == 0 {
-        return a;
+        // Press counts too large for an i64 can't be paid for anyway
+        Some((a.try_into().ok()?, b.try_into().ok()?))
     }
-    return gcd(b, a % b);
 }
 
 #[derive(Debug, Error)]
@@ -68,13 +97,17 @@ enum ClawConfigError {
     InvalidBlock,
     #[error("Invalid number")]
     InvalidNumber(#[from] std::num::ParseIntError),
+    #[error("Prize overflowed when offset")]
+    PrizeOverflow,
+    #[error("Token cost overflowed")]
+    PriceOverflow,
 }
 
 fn parse_input(input: &str) -> Result<Vec<ClawConfig>, ClawConfigError> {
     // Regex to match the three lines of each block
-    // Written by CoPilot.
+    // Written by CoPilot. Signs are optional so buttons can move in either direction.
     let re = Regex::new(
-        r"Button A: X\+(\d+), Y\+(\d+)\s+Button B: X\+(\d+), Y\+(\d+)\s+Prize: X=(\d+), Y=(\d+)",
+        r"Button A: X([+-]?\d+), Y([+-]?\d+)\s+Button B: X([+-]?\d+), Y([+-]?\d+)\s+Prize: X=(-?\d+), Y=(-?\d+)",
     )
     .unwrap();
 
@@ -125,12 +158,121 @@ fn parse_input(input: &str) -> Result<Vec<ClawConfig>, ClawConfigError> {
 //     Some((n1, n2))
 // }
 
-fn calculate_price(button_a: i64, button_b: i64) -> i64 {
-    button_a * 3 + button_b
+/// Token cost of pressing the button at `index` once. Button A costs 3 and every other button 1.
+fn button_cost(index: usize) -> i64 {
+    if index == 0 {
+        DEFAULT_COSTS.0
+    } else {
+        DEFAULT_COSTS.1
+    }
+}
+
+/// Finds the non-negative number of presses of each button that reaches the prize.
+///
+/// Two buttons take the fast `spider_hater_4_equation` path. With more buttons than equations
+/// the presses of all but one non-parallel pair of buttons are enumerated, and the pair is solved
+/// exactly for the remainder. The enumeration is only bounded for non-negative deltas, so any
+/// negative delta in that case gives None. Of all solutions the one with the lowest token cost
+/// is returned, ties going to the lexicographically smallest presses.
+///
+/// None means the prize is unreachable or the system is degenerate. Two parallel buttons, or
+/// more buttons without a single non-parallel pair, always give None, even if the prize lies on
+/// their line and could be reached.
+pub fn solve_linear(buttons: &[(i64, i64)], prize: (i64, i64)) -> Option<Vec<i64>> {
+    match buttons {
+        [] => (prize == (0, 0)).then(Vec::new),
+        [(dx, dy)] => {
+            // A single button must reach the prize along both axes with the same press count
+            let presses = if *dx != 0 {
+                prize.0 / dx
+            } else if *dy != 0 {
+                prize.1 / dy
+            } else {
+                0
+            };
+            (presses >= 0 && (dx * presses, dy * presses) == prize).then(|| vec![presses])
+        }
+        [a, b] => {
+            let (a, b, _) = ClawConfig::new(*a, *b, prize).solve()?;
+            Some(vec![a, b])
+        }
+        _ => solve_underdetermined(buttons, prize),
+    }
+}
+
+fn solve_underdetermined(buttons: &[(i64, i64)], prize: (i64, i64)) -> Option<Vec<i64>> {
+    if buttons.iter().any(|&(dx, dy)| dx < 0 || dy < 0) {
+        return None;
+    }
+
+    // Find a pair of buttons that can be solved exactly, the rest are enumerated
+    let (i, j) = (0..buttons.len())
+        .flat_map(|i| (i + 1..buttons.len()).map(move |j| (i, j)))
+        .find(|&(i, j)| {
+            let ((ax, ay), (bx, by)) = (buttons[i], buttons[j]);
+            ax * by - ay * bx != 0
+        })?;
+    let free = (0..buttons.len())
+        .filter(|&k| k != i && k != j)
+        .collect::<Vec<_>>();
+
+    let mut presses = vec![0; buttons.len()];
+    let mut best: Option<(i64, Vec<i64>)> = None;
+    enumerate_presses(buttons, &free, (i, j), prize, &mut presses, &mut best);
+
+    best.map(|(_, presses)| presses)
+}
+
+fn enumerate_presses(
+    buttons: &[(i64, i64)],
+    free: &[usize],
+    pair: (usize, usize),
+    remaining: (i64, i64),
+    presses: &mut Vec<i64>,
+    best: &mut Option<(i64, Vec<i64>)>,
+) {
+    let Some((&k, rest)) = free.split_first() else {
+        let (i, j) = pair;
+        if let Some((a, b, _)) = ClawConfig::new(buttons[i], buttons[j], remaining).solve() {
+            presses[i] = a;
+            presses[j] = b;
+            let cost = presses
+                .iter()
+                .enumerate()
+                .map(|(index, &n)| n * button_cost(index))
+                .sum::<i64>();
+            let better = match best {
+                Some((best_cost, best_presses)) => {
+                    (cost, presses.as_slice()) < (*best_cost, best_presses.as_slice())
+                }
+                None => true,
+            };
+            if better {
+                *best = Some((cost, presses.clone()));
+            }
+        }
+        return;
+    };
+
+    // Bound the presses by the first axis the button would overshoot
+    let (dx, dy) = buttons[k];
+    let limit = match (dx, dy) {
+        (0, 0) => 0,
+        (0, dy) => remaining.1 / dy,
+        (dx, 0) => remaining.0 / dx,
+        (dx, dy) => (remaining.0 / dx).min(remaining.1 / dy),
+    };
+
+    for n in 0..=limit {
+        presses[k] = n;
+        let remaining = (remaining.0 - n * dx, remaining.1 - n * dy);
+        enumerate_presses(buttons, rest, pair, remaining, presses, best);
+    }
+    presses[k] = 0;
 }
 
 fn main() -> Result<()> {
-    let input = std::fs::read_to_string("input.txt")?;
+    let input = read_input(std::env::args().nth(1))?;
 
     println!("Part 1: {}", part1(&input)?);
     println!("Part 2: {}", part2(&input)?);
@@ -139,54 +281,56 @@ fn main() -> Result<()> {
 }
 
 fn part1(input: &str) -> Result<i64> {
+    part1_with_costs(input, DEFAULT_COSTS)
+}
+
+/// Same as `part1`, but with the token costs of the A and B buttons given as `(cost_a, cost_b)`.
+fn part1_with_costs(input: &str, (cost_a, cost_b): (i64, i64)) -> Result<i64> {
     let configs = parse_input(input)?;
     // Calculate the sum of the prices of the winnable games, using Spider Haters rule.
+    // Each button can be pressed at most 100 times.
     let sum = configs
         .iter()
         .filter_map(|c| {
-            if !c.winnable() {
-                return None;
-            }
-
-            let (a, b) = c.spider_hater_4_equation()?;
-
-            if a > 100 || b > 100 {
-                None
-            } else {
-                if a < 0 || b < 0 {
-                    println!("Negative values: a={}, b={}", a, b);
-                }
-                Some(calculate_price(a, b))
-            }
+            let (a, b) = c.solve_bounded(Some(100))?;
+            Some(c.price(a, b, cost_a, cost_b))
         })
-        .sum::<i64>();
+        .try_fold(0i64, |acc, price| acc.checked_add(price?))
+        .ok_or(ClawConfigError::PriceOverflow)?;
 
     Ok(sum)
 }
 
 fn part2(input: &str) -> Result<i64> {
+    part2_with_costs(input, DEFAULT_COSTS)
+}
+
+/// Same as `part2`, but with the token costs of the A and B buttons given as `(cost_a, cost_b)`.
+fn part2_with_costs(input: &str, (cost_a, cost_b): (i64, i64)) -> Result<i64> {
     let mut configs = parse_input(input)?;
 
     for c in &mut configs {
-        c.prize.0 += TRILLION;
-        c.prize.1 += TRILLION;
+        c.prize.0 = c
+            .prize
+            .0
+            .checked_add(TRILLION)
+            .ok_or(ClawConfigError::PrizeOverflow)?;
+        c.prize.1 = c
+            .prize
+            .1
+            .checked_add(TRILLION)
+            .ok_or(ClawConfigError::PrizeOverflow)?;
     }
 
+    // Winnable machines whose price overflows are an error, not skipped like unwinnable ones
     let sum = configs
         .iter()
         .filter_map(|c| {
-            if !c.winnable() {
-                return None;
-            }
-
-            let (a, b) = c.spider_hater_4_equation()?;
-
-            if a < 0 || b < 0 {
-                println!("Negative values: a={}, b={}", a, b);
-            }
-            Some(calculate_price(a, b))
+            let (a, b) = c.solve_bounded(None)?;
+            Some(c.price(a, b, cost_a, cost_b))
         })
-        .sum::<i64>();
+        .try_fold(0i64, |acc, price| acc.checked_add(price?))
+        .ok_or(ClawConfigError::PriceOverflow)?;
 
     Ok(sum)
 }
@@ -224,7 +368,8 @@ mod tests {
 
     #[test]
     fn test_part2() {
-        let expected = 0;
+        // Only the second and fourth machines are winnable once the prize is offset
+        let expected = 875318608908;
         let actual = part2(INPUT).unwrap();
         assert_eq!(actual, expected);
     }
@@ -236,16 +381,6 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
-    #[test]
-    fn test_cramer_rule() {
-        let (dx_1, dy_1, dx_2, dy_2) = (94, 34, 22, 67);
-        let (t_x, t_y) = (8400, 5400);
-        let expected = Some((80, 40));
-        let actual = cramer_rule(dx_1, dy_1, dx_2, dy_2, t_x, t_y);
-
-        assert_eq!(actual, expected);
-    }
-
     #[test]
     fn test_spider_hater() {
         let (ax, ay, bx, by) = (94, 34, 22, 67);
@@ -256,11 +391,145 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn test_negative_button_delta() {
+        let input = r#"
+        Button A: X-3, Y+5
+        Button B: X+4, Y+1
+        Prize: X=6, Y=13
+"#;
+        let configs = parse_input(input).unwrap();
+        assert_eq!(configs, vec![ClawConfig::new((-3, 5), (4, 1), (6, 13))]);
+        assert_eq!(configs[0].spider_hater_4_equation(), Some((2, 3)));
+        assert_eq!(part1(input).unwrap(), 9);
+    }
+
+    #[test]
+    fn test_solve() {
+        let config = create_test_configs()[0];
+        assert_eq!(config.solve(), Some((80, 40, 280)));
+        assert_eq!(create_test_configs()[1].solve(), None);
+    }
+
+    #[test]
+    fn test_spider_hater_large_values() {
+        // by * ax alone is 1.6e19, past the i64 limit
+        let config = ClawConfig::new(
+            (4_000_000_000, 1),
+            (1, 4_000_000_000),
+            (8_000_000_005, 20_000_000_002),
+        );
+        assert_eq!(config.spider_hater_4_equation(), Some((2, 5)));
+
+        let config = ClawConfig::new((4_000_000_000, 1), (1, 4_000_000_000), (1, 1));
+        assert_eq!(config.spider_hater_4_equation(), None);
+
+        // A press count that doesn't fit in an i64
+        let config = ClawConfig::new((1, 0), (0, 2), (i64::MAX, -2));
+        assert_eq!(config.spider_hater_4_equation(), Some((i64::MAX, -1)));
+        let config = ClawConfig::new((-1, 0), (0, 1), (i64::MIN, 0));
+        assert_eq!(config.spider_hater_4_equation(), None);
+    }
+
+    #[test]
+    fn test_part2_prize_overflow() {
+        let input = r#"
+        Button A: X+1, Y+0
+        Button B: X+0, Y+1
+        Prize: X=9223372036854775807, Y=0
+"#;
+        assert!(part2(input).is_err());
+    }
+
+    #[test]
+    fn test_solve_bounded() {
+        // Needs more than 100 presses of A
+        let config = ClawConfig::new((1, 0), (0, 1), (150, 50));
+        assert_eq!(config.solve_bounded(Some(100)), None);
+        assert_eq!(config.solve_bounded(Some(150)), Some((150, 50)));
+        assert_eq!(config.solve_bounded(None), Some((150, 50)));
+
+        // Negative presses are never allowed, bounded or not
+        let config = ClawConfig::new((1, 0), (0, 1), (-1, 50));
+        assert_eq!(config.solve_bounded(Some(100)), None);
+        assert_eq!(config.solve_bounded(None), None);
+    }
+
+    #[test]
+    fn test_solve_linear_two_buttons() {
+        let configs = create_test_configs();
+        let expected = [Some(vec![80, 40]), None, Some(vec![38, 86]), None];
+
+        for (config, expected) in configs.iter().zip(expected) {
+            let actual = solve_linear(&[config.button_a, config.button_b], config.prize);
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn test_solve_linear_three_buttons() {
+        // Pressing A and B once each costs 4 tokens, pressing C twice only costs 2
+        let buttons = [(2, 0), (0, 2), (1, 1)];
+        assert_eq!(solve_linear(&buttons, (2, 2)), Some(vec![0, 0, 2]));
+        // Off the diagonal, C does what it can and A makes up the rest
+        assert_eq!(solve_linear(&buttons, (4, 2)), Some(vec![1, 0, 2]));
+        assert_eq!(solve_linear(&buttons, (1, 2)), None);
+    }
+
+    #[test]
+    fn test_solve_linear_degenerate() {
+        // Pressing B twice would do, but parallel buttons are treated as degenerate
+        assert_eq!(solve_linear(&[(1, 1), (2, 2)], (4, 4)), None);
+        assert_eq!(solve_linear(&[(1, 1), (2, 2), (3, 3)], (6, 6)), None);
+    }
+
     #[test]
     fn test_calculate_price() {
+        let config = create_test_configs()[0];
         let expected = 280;
-        let actual = calculate_price(80, 40);
-        assert_eq!(actual, expected);
+        let actual = config.price(80, 40, DEFAULT_COSTS.0, DEFAULT_COSTS.1);
+        assert_eq!(actual, Some(expected));
+        assert_eq!(config.price(80, 40, 1, 1), Some(120));
+    }
+
+    #[test]
+    fn test_price_overflow() {
+        // Solvable, but i64::MAX presses of A cost 3 tokens each
+        let config = ClawConfig::new((1, 0), (0, 1), (i64::MAX, 0));
+        assert_eq!(config.solve_bounded(None), Some((i64::MAX, 0)));
+        assert_eq!(config.price(i64::MAX, 0, 3, 1), None);
+        assert_eq!(config.solve(), None);
+        assert_eq!(
+            config.solve_with_costs((1, 1)),
+            Some((i64::MAX, 0, i64::MAX))
+        );
+
+        // After the offset, B has to be pressed i64::MAX times and A a trillion times
+        let input = r#"
+        Button A: X+0, Y+1
+        Button B: X+1, Y+0
+        Prize: X=9223362036854775807, Y=0
+"#;
+        let error = part2(input).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<ClawConfigError>(),
+            Some(ClawConfigError::PriceOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_custom_costs() {
+        assert_eq!(part1_with_costs(INPUT, DEFAULT_COSTS).unwrap(), 480);
+        // Only the first and third machines are winnable, 80 + 40 and 38 + 86 presses
+        assert_eq!(part1_with_costs(INPUT, (1, 1)).unwrap(), 244);
+        assert_ne!(
+            part2_with_costs(INPUT, (1, 1)).unwrap(),
+            part2(INPUT).unwrap()
+        );
+        assert_eq!(
+            create_test_configs()[0].solve_with_costs((1, 1)),
+            Some((80, 40, 120))
+        );
     }
 
     #[test]
@@ -4,6 +4,7 @@ use std::{
 };
 
 use anyhow::Result;
+use common::read_input;
 use image::{ImageBuffer, Rgb};
 use regex::Regex;
 
@@ -78,6 +79,30 @@ impl Map {
 
         (nw, ne, sw, se)
     }
+
+    // Classifies a point into the same quadrants as `quadrants`, without building the sets.
+    // Returns 0 (NW), 1 (NE), 2 (SW) or 3 (SE), and None for points on the center lines or out of bounds.
+    pub fn quadrant_of(&self, p: Point) -> Option<usize> {
+        if p.x < 0 || p.x >= self.width || p.y < 0 || p.y >= self.height {
+            return None;
+        }
+
+        let half_width = self.width / 2;
+        let half_height = self.height / 2;
+        // Center, so skip
+        if p.x == half_width || p.y == half_height {
+            return None;
+        }
+        let bottom = p.y > half_height;
+        let right = p.x > half_width;
+
+        match (bottom, right) {
+            (false, false) => Some(0),
+            (false, true) => Some(1),
+            (true, false) => Some(2),
+            (true, true) => Some(3),
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -92,57 +117,70 @@ impl Robot {
     }
     // Advances the position of the robot by the given amount of seconds
     pub fn advance_seconds(&mut self, map: &Map, seconds: i64) -> Point {
+        self.pos = self.position_after(map, seconds);
+        self.pos
+    }
+
+    // Calculates where the robot will be after the given amount of seconds, without moving it
+    pub fn position_after(&self, map: &Map, seconds: i64) -> Point {
         let adjusted_vel = Point::new(self.vel.x * seconds, self.vel.y * seconds);
         let Point { x, y } = self.pos + adjusted_vel;
         let wrapped_x = (x % map.width + map.width) % map.width;
         let wrapped_y = (y % map.height + map.height) % map.height;
-        let new_pos = Point::new(wrapped_x, wrapped_y);
-        self.pos = new_pos;
-        self.pos
+        Point::new(wrapped_x, wrapped_y)
     }
 }
 
 fn main() -> Result<()> {
-    let input = std::fs::read_to_string("input.txt")?;
-    let robots = parse_robots(&input)?;
-    let map = Map::new(101, 103);
-
-    let part_1 = solve_part_1(&robots, &map);
+    // The input path can be given as the first argument, defaulting to input.txt
+    let mut args = std::env::args().skip(1);
+    let input = read_input(args.next())?;
+
+    // The grid size can be given as two more optional arguments, defaulting to the puzzle's size
+    let width = args
+        .next()
+        .map(|arg| arg.parse())
+        .transpose()?
+        .unwrap_or(101);
+    let height = args
+        .next()
+        .map(|arg| arg.parse())
+        .transpose()?
+        .unwrap_or(103);
+
+    let (part_1, part_2) = run(&input, width, height)?;
     println!("Part 1: {}", part_1);
 
-    // Part 2 doesn't return anything, but saves images
-    solve_part_2(&robots, &map);
+    match part_2 {
+        Some(seconds) => println!("Part 2: {}", seconds),
+        // Fall back to saving images of candidate frames for manual inspection
+        None => solve_part_2(&parse_robots(&input)?, &Map::new(width, height)),
+    }
 
     Ok(())
 }
 
-// This probably does too much, but I'll leave my regrets for Part 2.
-fn solve_part_1(robots: &[Robot], map: &Map) -> usize {
-    // Create an owned copy of the robots
-    let robots = robots.to_vec();
+// Solves both parts for a grid of the given size.
+// Part 2 is None if no frame without overlapping robots was found.
+fn run(input: &str, width: i64, height: i64) -> Result<(usize, Option<i64>)> {
+    let robots = parse_robots(input)?;
+    let map = Map::new(width, height);
 
+    let part_1 = solve_part_1(&robots, &map);
+    let part_2 = find_easter_egg(&robots, &map);
+
+    Ok((part_1, part_2))
+}
+
+fn solve_part_1(robots: &[Robot], map: &Map) -> usize {
     const SECONDS: i64 = 100;
 
-    let mut pos_map = HashMap::new();
     // Four index array representing the number of robots in each quadrant.
     let mut counters = [0; 4];
-    let (nw, ne, sw, se) = map.quadrants();
-    // Helper to quickly add all points to the `pos_map`
-    let mut insert_points = |points: HashSet<Point>, index: usize| {
-        for point in points {
-            pos_map.insert(point, index);
-        }
-    };
-    // Insert all points into the map
-    insert_points(nw, 0);
-    insert_points(ne, 1);
-    insert_points(sw, 2);
-    insert_points(se, 3);
 
-    for mut robot in robots {
-        robot.advance_seconds(map, SECONDS);
+    for position in positions_after(robots, map, SECONDS) {
         // See if the robot is in a quadrant.
-        if let Some(&index) = pos_map.get(&robot.pos) {
+        if let Some(index) = map.quadrant_of(position) {
             // If so, increment the relevant counter.
             counters[index] += 1;
         }
@@ -152,6 +190,34 @@ fn solve_part_1(robots: &[Robot], map: &Map) -> usize {
     calculate_safety(counters).unwrap()
 }
 
+// Calculates the position of every robot after the given amount of seconds, leaving the robots untouched.
+fn positions_after(robots: &[Robot], map: &Map, seconds: i64) -> Vec<Point> {
+    robots
+        .iter()
+        .map(|robot| robot.position_after(map, seconds))
+        .collect()
+}
+
+// Dumps the position of every robot after the given amount of seconds as CSV, one `id,x,y` row per robot.
+// The id is the robot's index in the input. Starts with a header row.
+pub fn dump_positions(robots: &[Robot], map: &Map, seconds: i64) -> String {
+    let mut csv = String::from("id,x,y\n");
+    for (id, Point { x, y }) in positions_after(robots, map, seconds).iter().enumerate() {
+        csv.push_str(&format!("{},{},{}\n", id, x, y));
+    }
+    csv
+}
+
+// Same as `dump_positions`, but as a JSON array of `{"id": _, "x": _, "y": _}` objects.
+pub fn dump_positions_json(robots: &[Robot], map: &Map, seconds: i64) -> String {
+    let objects = positions_after(robots, map, seconds)
+        .iter()
+        .enumerate()
+        .map(|(id, Point { x, y })| format!(r#"{{"id":{},"x":{},"y":{}}}"#, id, x, y))
+        .collect::<Vec<_>>();
+    format!("[{}]", objects.join(","))
+}
+
 fn calculate_safety(quadrants: [usize; 4]) -> Option<usize> {
     // Multiply all the quadrant counters together
     // Returns None if the product overflows
@@ -215,29 +281,150 @@ fn solve_part_2(robots: &[Robot], map: &Map) {
     }
 }
 
+// Returns the first second at which no two robots share a tile, which is when the tree appears.
+// Positions repeat every width * height seconds, so there's no point looking any further.
+fn find_easter_egg(robots: &[Robot], map: &Map) -> Option<i64> {
+    let mut robots = robots.to_vec();
+    let mut occupied = HashSet::new();
+
+    for second in 1..=map.width * map.height {
+        for robot in robots.iter_mut() {
+            robot.advance_seconds(map, 1);
+        }
+
+        occupied.clear();
+        if robots.iter().all(|robot| occupied.insert(robot.pos)) {
+            return Some(second);
+        }
+    }
+
+    None
+}
+
+// Counts the tiles with two or more robots on them for each of the first `seconds` seconds.
+// Entry `t` is the count after `t + 1` seconds, so the tree frame shows up as a zero.
+pub fn overlap_history(robots: &[Robot], map: &Map, seconds: i64) -> Vec<usize> {
+    let mut robots = robots.to_vec();
+
+    (0..seconds)
+        .map(|_| {
+            for robot in robots.iter_mut() {
+                robot.advance_seconds(map, 1);
+            }
+            occupancy(&robots, map)
+                .values()
+                .filter(|&&count| count >= 2)
+                .count()
+        })
+        .collect()
+}
+
+// Counts the robots which have at least one other robot directly above, below, left or right of them.
+// Robots sharing a tile don't count as neighbours. The tree frame has lots of robots packed together,
+// so this peaks sharply on that second.
+pub fn clustering_score(robots: &[Robot], map: &Map) -> usize {
+    let occupied: HashSet<Point> = robots.iter().map(|robot| robot.pos).collect();
+    let offsets = [
+        Point::new(0, -1),
+        Point::new(0, 1),
+        Point::new(-1, 0),
+        Point::new(1, 0),
+    ];
+
+    robots
+        .iter()
+        .filter(|robot| {
+            offsets.iter().any(|&offset| {
+                let Point { x, y } = robot.pos + offset;
+                // Neighbours off the edge of the map don't count
+                (0..map.width).contains(&x)
+                    && (0..map.height).contains(&y)
+                    && occupied.contains(&Point::new(x, y))
+            })
+        })
+        .count()
+}
+
 // Saves an image of the current state of the robots to a bitmap file.
 fn save_image(robots: &[Robot], map: &Map, i: usize) {
+    save_image_styled(robots, map, i, Rgb([0, 0, 0]), Rgb([255, 255, 255]), false);
+}
+
+// Same as `save_image`, but with the given robot and background colours.
+// With intensity on, tiles are shaded by how many robots are on them, the most crowded tile getting the full robot colour.
+fn save_image_styled(
+    robots: &[Robot],
+    map: &Map,
+    i: usize,
+    robot_color: Rgb<u8>,
+    bg: Rgb<u8>,
+    intensity: bool,
+) {
     let folder_path = "images";
     std::fs::create_dir_all(folder_path).unwrap();
 
     let file_name = format!("{}/image_{:05}.bmp", folder_path, i + 1);
 
-    let mut img = ImageBuffer::new(map.width as u32, map.height as u32);
+    let img = render_frame_styled(robots, map, robot_color, bg, intensity);
 
-    // Fill the image with a background color (e.g., white)
-    for pixel in img.pixels_mut() {
-        *pixel = Rgb::<u8>([255, 255, 255]);
+    println!("Saved image: {}", file_name);
+
+    img.save(file_name).unwrap();
+}
+
+// Renders the current state of the robots to an in-memory image.
+pub fn render_frame(robots: &[Robot], map: &Map) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    // Robots are black on a white background
+    render_frame_styled(robots, map, Rgb([0, 0, 0]), Rgb([255, 255, 255]), false)
+}
+
+// Renders the robots with the given colours, see `save_image_styled`.
+fn render_frame_styled(
+    robots: &[Robot],
+    map: &Map,
+    robot_color: Rgb<u8>,
+    bg: Rgb<u8>,
+    intensity: bool,
+) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let mut img = ImageBuffer::from_pixel(map.width as u32, map.height as u32, bg);
+
+    let counts = occupancy(robots, map);
+    let most = counts.values().copied().max().unwrap_or(1);
+
+    for (point, count) in counts {
+        let color = if intensity {
+            // Blend from the background towards the robot colour
+            let blend = |b: u8, r: u8| {
+                let (b, r) = (b as usize, r as usize);
+                ((b * (most - count) + r * count) / most) as u8
+            };
+            Rgb([
+                blend(bg[0], robot_color[0]),
+                blend(bg[1], robot_color[1]),
+                blend(bg[2], robot_color[2]),
+            ])
+        } else {
+            robot_color
+        };
+        img.put_pixel(point.x as u32, point.y as u32, color);
     }
 
+    img
+}
+
+// Counts the number of robots on each tile of the map, skipping any that are out of bounds.
+fn occupancy(robots: &[Robot], map: &Map) -> HashMap<Point, usize> {
+    let mut counts = HashMap::new();
+
     for robot in robots {
-        let x = robot.pos.x as u32;
-        let y = robot.pos.y as u32;
-        img.put_pixel(x, y, Rgb([0, 0, 0])); // Assuming robots are black
+        let Point { x, y } = robot.pos;
+        if x < 0 || x >= map.width || y < 0 || y >= map.height {
+            continue;
+        }
+        *counts.entry(robot.pos).or_default() += 1;
     }
 
-    println!("Saved image: {}", file_name);
-
-    img.save(file_name).unwrap();
+    counts
 }
 
 fn parse_robots(input: &str) -> Result<Vec<Robot>> {
@@ -324,6 +511,49 @@ mod tests {
         assert_eq!(robot.advance_seconds(&map, 5), Point::new(1, 3));
     }
 
+    #[test]
+    fn test_positions_after() {
+        let map = create_map();
+        let robots = create_robots();
+
+        let mut stepped = robots.clone();
+        for _ in 0..100 {
+            for robot in stepped.iter_mut() {
+                robot.advance_seconds(&map, 1);
+            }
+        }
+        let expected: Vec<Point> = stepped.iter().map(|robot| robot.pos).collect();
+
+        assert_eq!(expected, positions_after(&robots, &map, 100));
+        // The robots themselves haven't moved
+        assert_eq!(robots, create_robots());
+    }
+
+    #[test]
+    fn test_dump_positions() {
+        let map = create_map();
+        let robots = create_robots();
+        let expected = positions_after(&robots, &map, 100);
+
+        let csv = dump_positions(&robots, &map, 100);
+        // Header plus one row per robot
+        assert_eq!(csv.lines().count(), robots.len() + 1);
+        assert_eq!(csv.lines().next(), Some("id,x,y"));
+
+        let json = dump_positions_json(&robots, &map, 100);
+        let re = Regex::new(r#"\{"id":(\d+),"x":(-?\d+),"y":(-?\d+)\}"#).unwrap();
+        let parsed: Vec<(usize, Point)> = re
+            .captures_iter(&json)
+            .map(|caps| {
+                let id = caps[1].parse().unwrap();
+                let point = Point::new(caps[2].parse().unwrap(), caps[3].parse().unwrap());
+                (id, point)
+            })
+            .collect();
+        assert!(json.starts_with('[') && json.ends_with(']'));
+        assert_eq!(parsed, expected.into_iter().enumerate().collect::<Vec<_>>());
+    }
+
     #[test]
     fn test_quadrants() {
         let map = create_map();
@@ -336,6 +566,23 @@ mod tests {
         assert_eq!(15, se.iter().len());
     }
 
+    #[test]
+    fn test_quadrant_of() {
+        let map = create_map();
+        let (nw, ne, sw, se) = map.quadrants();
+
+        for y in 0..map.height {
+            for x in 0..map.width {
+                let point = Point::new(x, y);
+                let expected = [&nw, &ne, &sw, &se]
+                    .iter()
+                    .position(|quadrant| quadrant.contains(&point));
+                assert_eq!(map.quadrant_of(point), expected);
+            }
+        }
+        assert_eq!(map.quadrant_of(Point::new(-1, 0)), None);
+    }
+
     #[test]
     fn test_parse_robots() {
         let robots = parse_robots(INPUT).unwrap();
@@ -351,4 +598,110 @@ mod tests {
         let actual = solve_part_1(&robots, &map);
         assert_eq!(expected, actual);
     }
+
+    #[test]
+    fn test_run() {
+        let (part_1, _) = run(INPUT, 11, 7).unwrap();
+        assert_eq!(12, part_1);
+    }
+
+    #[test]
+    fn test_find_easter_egg() {
+        let map = Map::new(5, 5);
+        // The first robot catches up with the stationary one after 1 second,
+        // then meets the third one after 2 seconds, while the third meets the stationary one after 3.
+        let robots = vec![
+            Robot::new(Point::new(0, 0), Point::new(1, 0)),
+            Robot::new(Point::new(1, 0), Point::new(0, 0)),
+            Robot::new(Point::new(4, 0), Point::new(-1, 0)),
+        ];
+        assert_eq!(find_easter_egg(&robots, &map), Some(4));
+
+        // Two robots moving in lockstep on the same tile never separate
+        let robots = vec![Robot::new(Point::new(0, 0), Point::new(1, 1)); 2];
+        assert_eq!(find_easter_egg(&robots, &map), None);
+    }
+
+    #[test]
+    fn test_overlap_history() {
+        let map = Map::new(5, 5);
+        // Same robots as the easter egg test, which first separate after 4 seconds
+        let robots = vec![
+            Robot::new(Point::new(0, 0), Point::new(1, 0)),
+            Robot::new(Point::new(1, 0), Point::new(0, 0)),
+            Robot::new(Point::new(4, 0), Point::new(-1, 0)),
+        ];
+        let history = overlap_history(&robots, &map, 5);
+
+        assert_eq!(history.len(), 5);
+        assert_eq!(history, vec![1, 1, 1, 0, 0]);
+        assert_eq!(history.iter().position(|&count| count == 0), Some(3));
+        assert!(overlap_history(&robots, &map, 0).is_empty());
+    }
+
+    #[test]
+    fn test_occupancy() {
+        let map = create_map();
+        let robots = create_robots();
+        let counts = occupancy(&robots, &map);
+
+        assert_eq!(counts.values().sum::<usize>(), robots.len());
+        // Two robots start on (3, 0)
+        assert_eq!(counts[&Point::new(3, 0)], 2);
+    }
+
+    #[test]
+    fn test_clustering_score() {
+        let map = create_map();
+        let still = Point::new(0, 0);
+        // A 2x2 block, every robot touches two others
+        let clustered: Vec<Robot> = [(4, 2), (5, 2), (4, 3), (5, 3)]
+            .iter()
+            .map(|&(x, y)| Robot::new(Point::new(x, y), still))
+            .collect();
+        // Only diagonal neighbours, which don't count
+        let scattered: Vec<Robot> = [(0, 0), (2, 2), (4, 4), (6, 6)]
+            .iter()
+            .map(|&(x, y)| Robot::new(Point::new(x, y), still))
+            .collect();
+
+        assert_eq!(clustering_score(&clustered, &map), 4);
+        assert_eq!(clustering_score(&scattered, &map), 0);
+        assert!(clustering_score(&clustered, &map) > clustering_score(&scattered, &map));
+    }
+
+    #[test]
+    fn test_render_frame_styled() {
+        let map = create_map();
+        let still = Point::new(0, 0);
+        // Two robots share (1, 1), one is alone on (3, 3)
+        let robots = vec![
+            Robot::new(Point::new(1, 1), still),
+            Robot::new(Point::new(1, 1), still),
+            Robot::new(Point::new(3, 3), still),
+        ];
+        let (robot_color, bg) = (Rgb([0, 0, 200]), Rgb([255, 255, 0]));
+
+        let img = render_frame_styled(&robots, &map, robot_color, bg, false);
+        assert_eq!(*img.get_pixel(1, 1), robot_color);
+        assert_eq!(*img.get_pixel(3, 3), robot_color);
+        assert_eq!(*img.get_pixel(0, 0), bg);
+
+        // The overlapping tile gets the full colour, the single robot is halfway there
+        let img = render_frame_styled(&robots, &map, robot_color, bg, true);
+        assert_eq!(*img.get_pixel(1, 1), robot_color);
+        assert_eq!(*img.get_pixel(3, 3), Rgb([127, 127, 100]));
+        assert_eq!(*img.get_pixel(0, 0), bg);
+    }
+
+    #[test]
+    fn test_render_frame() {
+        let map = create_map();
+        let robots = create_robots();
+        let img = render_frame(&robots, &map);
+
+        assert_eq!(img.dimensions(), (11, 7));
+        assert_eq!(*img.get_pixel(0, 0), Rgb([0, 0, 0]));
+        assert_eq!(*img.get_pixel(1, 0), Rgb([255, 255, 255]));
+    }
 }
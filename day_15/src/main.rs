@@ -1,15 +1,28 @@
 use std::{
     collections::{HashMap, HashSet},
     hash::Hash,
+    str::FromStr,
 };
 
 use anyhow::Result;
+use common::read_input;
 use thiserror::Error;
 
 /// Represents the main actor in the simulation.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Robot(Point);
 
+impl Robot {
+    pub fn new(p: Point) -> Self {
+        Robot(p)
+    }
+
+    /// The point the robot is standing on.
+    pub fn position(&self) -> Point {
+        self.0
+    }
+}
+
 // Represents a 2d point in the graph.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Point {
@@ -22,6 +35,14 @@ impl Point {
         Self { x, y }
     }
 
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+
+    pub fn y(&self) -> i32 {
+        self.y
+    }
+
     /// Apply a movement to the point, returning the new point.
     pub fn apply_movement(self, movement: Movement) -> Self {
         match movement {
@@ -44,6 +65,21 @@ impl Point {
         }
     }
 
+    /// Apply a movement n times, returning the new point.
+    /// A negative n moves the opposite way, and zero leaves the point where it is.
+    pub fn apply_movement_n(self, movement: Movement, n: i32) -> Self {
+        let (dx, dy) = match movement {
+            Movement::Up => (0, -1),
+            Movement::Down => (0, 1),
+            Movement::Left => (-1, 0),
+            Movement::Right => (1, 0),
+        };
+        Self {
+            x: self.x + dx * n,
+            y: self.y + dy * n,
+        }
+    }
+
     /// Calculate the GPS score of the point.
     pub fn gps_coordinate_score(self) -> i32 {
         self.x + self.y * 100
@@ -58,6 +94,31 @@ pub enum Tile {
     Box,
 }
 
+impl TryFrom<char> for Tile {
+    type Error = ParseInputError;
+
+    /// Only covers the simple tiles, the robot and big box halves need extra handling by the parser.
+    fn try_from(c: char) -> Result<Self, Self::Error> {
+        match c {
+            '#' => Ok(Tile::Wall),
+            '.' => Ok(Tile::Empty),
+            'O' => Ok(Tile::Box),
+            _ => Err(ParseInputError::InvalidMapCharacter(c)),
+        }
+    }
+}
+
+/// Counts of what happened over a simulation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SimStats {
+    /// Instructions where the robot moved.
+    pub moved: usize,
+    /// Instructions where the robot was blocked by a wall, directly or through boxes.
+    pub blocked: usize,
+    /// Total number of box moves across all instructions.
+    pub boxes_pushed: usize,
+}
+
 /// A graph representing a 2D grid of nodes.
 /// Each node has a position and a tile type.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -71,6 +132,22 @@ pub struct Graph {
 }
 
 impl Graph {
+    /// The size of the graph as (width, height).
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// The number of distinct nodes in the graph. A big box only counts once, even though it covers two tiles.
+    pub fn node_count(&self) -> usize {
+        self.node_storage.len()
+    }
+
+    /// The tile at the given point, or None if the point is outside the graph.
+    pub fn tile_at(&self, p: Point) -> Option<Tile> {
+        let id = self.nodes.get(&p)?;
+        self.node_storage.get(id).map(|node| node.tile)
+    }
+
     /// Creates a normalized representation of the graph, without respect to specific node ids.
     /// This is useful for comparing graphs for equality.
     pub fn normalize(&self) -> HashMap<Point, Node> {
@@ -180,8 +257,50 @@ impl Graph {
         }
     }
 
+    /// Like `process_instructions`, but also counts what each instruction did.
+    pub fn simulate_with_stats(
+        &self,
+        robot: &Robot,
+        instructions: &Instructions,
+    ) -> (Graph, Robot, SimStats) {
+        let (mut graph, mut robot) = (self.clone(), *robot);
+        let mut stats = SimStats::default();
+        for movement in &instructions.movements {
+            match graph.update(&mut robot, *movement) {
+                Some(boxes) => {
+                    stats.moved += 1;
+                    stats.boxes_pushed += boxes;
+                }
+                None => stats.blocked += 1,
+            }
+        }
+
+        (graph, robot, stats)
+    }
+
+    /// Like `process_instructions`, but also finds where the simulation stopped making progress.
+    /// Returns the index of the first instruction after which nothing ever moves again,
+    /// or the number of instructions if the last one still moved something.
+    pub fn run_until_stable(
+        &self,
+        robot: &Robot,
+        instructions: &Instructions,
+    ) -> (Graph, Robot, usize) {
+        let (mut graph, mut robot) = (self.clone(), *robot);
+        let mut stable_from = 0;
+        for (i, movement) in instructions.movements.iter().enumerate() {
+            // Blocked instructions leave everything as it was.
+            if graph.update(&mut robot, *movement).is_some() {
+                stable_from = i + 1;
+            }
+        }
+
+        (graph, robot, stable_from)
+    }
+
     /// Updates the graph with the robot's intended movement for a single instruction.
-    fn update(&mut self, robot: &mut Robot, movement: Movement) {
+    /// Returns the number of boxes pushed, or None if the robot was blocked.
+    fn update(&mut self, robot: &mut Robot, movement: Movement) -> Option<usize> {
         let new_pos = robot.0.apply_movement(movement);
         // Initialize the search frontier with the robot's intended movement.
         let mut frontier = HashSet::from([new_pos]);
@@ -209,7 +328,7 @@ impl Graph {
 
             // Step 2. Check if any of the nodes are walls.
             if nodes.iter().any(|(_, node)| node.tile == Tile::Wall) {
-                return; // Skips the movement
+                return None; // Skips the movement
             }
 
             // Step 3. Check if all nodes are empty.
@@ -242,18 +361,24 @@ impl Graph {
                 self.move_box(*movable_box, movement);
             }
         }
+
+        Some(queue.len())
     }
 
     /// Calculate the total GPS score of every box in the graph.
     pub fn gps_scores(&self) -> usize {
+        self.gps_score_where(|node| node.tile == Tile::Box)
+    }
+
+    /// Calculate the total GPS score of every node matching the predicate.
+    pub fn gps_score_where<F: Fn(&Node) -> bool>(&self, pred: F) -> usize {
         let mut scores = HashMap::new();
-        // Get all the boxes and calculate their GPS scores.
+        // Get all the matching nodes and calculate their GPS scores.
         // For big boxes, use the score of the left part.
         // But use the lower score as the score for the box.
         self.nodes
             .iter()
-            // We only care about boxes.
-            .filter(|(_, id)| self.node_storage.get(id).unwrap().tile == Tile::Box)
+            .filter(|(_, id)| pred(self.node_storage.get(id).unwrap()))
             .for_each(|(point, id)| {
                 let score = point.gps_coordinate_score() as usize;
                 scores
@@ -380,6 +505,33 @@ pub struct Instructions {
     movements: Vec<Movement>,
 }
 
+impl Instructions {
+    pub fn new(movements: Vec<Movement>) -> Self {
+        Self { movements }
+    }
+
+    /// The movements in the order they are applied.
+    pub fn movements(&self) -> &[Movement] {
+        &self.movements
+    }
+
+    pub fn len(&self) -> usize {
+        self.movements.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.movements.is_empty()
+    }
+}
+
+impl FromStr for Instructions {
+    type Err = ParseInputError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_instructions(s)
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Movement {
     Up,
@@ -389,6 +541,27 @@ pub enum Movement {
 }
 
 impl Movement {
+    /// All four movements, in declaration order.
+    pub fn all() -> [Self; 4] {
+        [
+            Movement::Up,
+            Movement::Down,
+            Movement::Left,
+            Movement::Right,
+        ]
+    }
+
+    /// Maps an instruction character (`^`, `v`, `<` or `>`) to its movement.
+    pub fn from_char(c: char) -> Option<Movement> {
+        match c {
+            '^' => Some(Movement::Up),
+            'v' => Some(Movement::Down),
+            '<' => Some(Movement::Left),
+            '>' => Some(Movement::Right),
+            _ => None,
+        }
+    }
+
     pub fn opposite(&self) -> Self {
         match self {
             Movement::Up => Movement::Down,
@@ -400,7 +573,7 @@ impl Movement {
 }
 
 fn main() -> Result<()> {
-    let input = std::fs::read_to_string("input.txt")?;
+    let input = read_input(std::env::args().nth(1))?;
 
     let part_1 = part_1(&input)?;
     println!("Part 1: {}", part_1);
@@ -426,6 +599,18 @@ fn solve(graph: Graph, instructions: Instructions, robot: Robot) -> Result<usize
     Ok(graph.gps_scores())
 }
 
+/// Sums the movements as if nothing was ever in the way, so opposite moves cancel out.
+/// Returns the `(x, y)` offset from where the robot started, with y growing downwards.
+pub fn net_displacement(instructions: &Instructions) -> (i32, i32) {
+    let end = instructions
+        .movements
+        .iter()
+        .fold(Point::new(0, 0), |point, &movement| {
+            point.apply_movement(movement)
+        });
+    (end.x(), end.y())
+}
+
 #[derive(Debug, Error)]
 pub enum ParseInputError {
     #[error("Missing map or instructions")]
@@ -509,9 +694,6 @@ fn parse_graph(input: &str) -> Result<(Graph, Robot), ParseInputError> {
             node_counter += 1;
             let point = Point::new(x as i32, y as i32);
             let node = match c {
-                '#' => Ok(Node::new(point, Tile::Wall)),
-                '.' => Ok(Node::new(point, Tile::Empty)),
-                'O' => Ok(Node::new(point, Tile::Box)),
                 '@' => {
                     // Robot is special, and always rests on an empty tile
                     robot = Some(Robot(point));
@@ -527,7 +709,7 @@ fn parse_graph(input: &str) -> Result<(Graph, Robot), ParseInputError> {
                     nodes.insert(point, id_counter - 1);
                     continue;
                 }
-                _ => Err(ParseInputError::InvalidMapCharacter(c)),
+                _ => Tile::try_from(c).map(|tile| Node::new(point, tile)),
             };
 
             let node = node?;
@@ -556,13 +738,7 @@ fn parse_instructions(input: &str) -> Result<Instructions, ParseInputError> {
     let movements = input
         .chars()
         .filter(|c| !c.is_whitespace())
-        .map(|c| match c {
-            '^' => Ok(Movement::Up),
-            'v' => Ok(Movement::Down),
-            '<' => Ok(Movement::Left),
-            '>' => Ok(Movement::Right),
-            _ => Err(ParseInputError::InvalidInstructionsCharacter(c)),
-        })
+        .map(|c| Movement::from_char(c).ok_or(ParseInputError::InvalidInstructionsCharacter(c)))
         .collect::<Result<Vec<_>, _>>()?;
 
     Ok(Instructions { movements })
@@ -695,12 +871,146 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_parse_instructions_from_str() {
+        let instructions = "<^v>".parse::<Instructions>().unwrap();
+        let expected = [
+            Movement::Left,
+            Movement::Up,
+            Movement::Down,
+            Movement::Right,
+        ];
+
+        assert_eq!(instructions.movements(), expected);
+        assert_eq!(instructions, Instructions::new(expected.to_vec()));
+        assert!(matches!(
+            "<^x>".parse::<Instructions>(),
+            Err(ParseInputError::InvalidInstructionsCharacter('x'))
+        ));
+    }
+
+    #[test]
+    fn test_net_displacement() {
+        let net = |s: &str| net_displacement(&s.parse::<Instructions>().unwrap());
+
+        assert_eq!(net("<>^v"), (0, 0));
+        assert_eq!(net(">>^"), (2, -1));
+        assert_eq!(net(""), (0, 0));
+        // The small sample's robot is blocked a few times, so it really ends up somewhere else
+        let (graph, instructions, robot) = parse_input(SMALL_INPUT, false).unwrap();
+        let (_, moved) = graph.process_instructions(&robot, &instructions);
+        assert_eq!(net_displacement(&instructions), (1, 2));
+        assert_eq!(
+            (
+                moved.position().x() - robot.position().x(),
+                moved.position().y() - robot.position().y()
+            ),
+            (2, 2)
+        );
+    }
+
+    #[test]
+    fn test_gps_score_where() {
+        let (graph, _, _) = parse_input(SMALL_INPUT, false).unwrap();
+
+        // The robot rests on an empty tile, so it counts too
+        let expected: usize = SMALL_INPUT
+            .trim()
+            .lines()
+            .take_while(|line| !line.trim().is_empty())
+            .enumerate()
+            .flat_map(|(y, line)| {
+                line.trim()
+                    .chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c == '.' || *c == '@')
+                    .map(move |(x, _)| x + y * 100)
+            })
+            .sum();
+
+        assert_eq!(
+            graph.gps_score_where(|node| node.tile == Tile::Empty),
+            expected
+        );
+        assert_eq!(
+            graph.gps_score_where(|node| node.tile == Tile::Box),
+            graph.gps_scores()
+        );
+    }
+
+    #[test]
+    fn test_tile_try_from_char() {
+        assert_eq!(Tile::try_from('#').unwrap(), Tile::Wall);
+        assert_eq!(Tile::try_from('.').unwrap(), Tile::Empty);
+        assert_eq!(Tile::try_from('O').unwrap(), Tile::Box);
+        assert!(matches!(
+            Tile::try_from('x'),
+            Err(ParseInputError::InvalidMapCharacter('x'))
+        ));
+    }
+
+    #[test]
+    fn test_movement_from_char() {
+        assert_eq!(Movement::from_char('^'), Some(Movement::Up));
+        assert_eq!(Movement::from_char('v'), Some(Movement::Down));
+        assert_eq!(Movement::from_char('<'), Some(Movement::Left));
+        assert_eq!(Movement::from_char('>'), Some(Movement::Right));
+        assert_eq!(Movement::from_char('V'), None);
+        assert_eq!(Movement::from_char('#'), None);
+    }
+
+    #[test]
+    fn test_movement_all() {
+        let all = Movement::all();
+        for movement in all {
+            assert_eq!(all.iter().filter(|&&m| m == movement).count(), 1);
+        }
+
+        // One step each way ends up back where it started
+        let origin = Point::new(3, 3);
+        let end = all
+            .iter()
+            .fold(origin, |p, &movement| p.apply_movement(movement));
+        assert_eq!(end, origin);
+    }
+
     #[test]
     fn test_calculate_gps_coordinate_score() {
         let point = Point::new(4, 1);
         assert_eq!(point.gps_coordinate_score(), 104);
     }
 
+    #[test]
+    fn test_apply_movement_n() {
+        let point = Point::new(5, 5);
+
+        assert_eq!(point.apply_movement_n(Movement::Up, 3), Point::new(5, 2));
+        assert_eq!(point.apply_movement_n(Movement::Down, 3), Point::new(5, 8));
+        assert_eq!(point.apply_movement_n(Movement::Left, 3), Point::new(2, 5));
+        assert_eq!(point.apply_movement_n(Movement::Right, 3), Point::new(8, 5));
+
+        for movement in Movement::all() {
+            // One step is the same as a single movement
+            assert_eq!(
+                point.apply_movement_n(movement, 1),
+                point.apply_movement(movement)
+            );
+            // Zero steps goes nowhere
+            assert_eq!(point.apply_movement_n(movement, 0), point);
+            // Negative steps go the opposite way and undo positive ones
+            assert_eq!(
+                point.apply_movement_n(movement, -2),
+                point.apply_movement_n(movement.opposite(), 2)
+            );
+            assert_eq!(
+                point
+                    .apply_movement_n(movement, 4)
+                    .apply_movement_n(movement, -4),
+                point
+            );
+        }
+    }
+
     #[test]
     fn test_calculate_simulate_large() {
         let (graph, _, robot) = parse_input(LARGE_INPUT, false).unwrap();
@@ -726,6 +1036,87 @@ mod tests {
         assert_eq!(graph, graph_exp);
     }
 
+    #[test]
+    fn test_robot_position() {
+        let (graph, _, _) = parse_input(SMALL_INPUT, false).unwrap();
+        // Start on an open tile next to a box, away from where the map puts the robot.
+        let robot = Robot::new(Point::new(1, 1));
+        let instructions = Instructions::new(vec![Movement::Right, Movement::Right]);
+
+        let (graph, robot) = graph.process_instructions(&robot, &instructions);
+
+        // The second move pushes the box along.
+        let position = robot.position();
+        assert_eq!((position.x(), position.y()), (3, 1));
+        assert_eq!(graph.tile_at(Point::new(4, 1)), Some(Tile::Box));
+    }
+
+    #[test]
+    fn test_public_accessors() {
+        // Only uses the public accessors, not the fields.
+        let (graph, _, robot) = parse_input(SMALL_INPUT, false).unwrap();
+
+        assert_eq!(graph.dimensions(), (8, 8));
+        assert_eq!(graph.node_count(), 64);
+        assert_eq!(graph.tile_at(Point::new(0, 0)), Some(Tile::Wall));
+        assert_eq!(graph.tile_at(Point::new(3, 1)), Some(Tile::Box));
+        // The robot always stands on an empty tile.
+        assert_eq!(graph.tile_at(robot.position()), Some(Tile::Empty));
+        assert_eq!(graph.tile_at(Point::new(8, 0)), None);
+
+        // Big boxes cover two tiles, but are a single node.
+        let (big, _, _) = parse_input(SMALL_INPUT, true).unwrap();
+        assert_eq!(big.dimensions(), (16, 8));
+        assert_eq!(big.tile_at(Point::new(6, 1)), Some(Tile::Box));
+        assert_eq!(big.tile_at(Point::new(7, 1)), Some(Tile::Box));
+        assert_eq!(big.node_count(), 16 * 8 - 6);
+    }
+
+    #[test]
+    fn test_simulate_with_stats() {
+        let (graph, instructions, robot) = parse_input(SMALL_INPUT, false).unwrap();
+
+        let (graph_exp, robot_exp) = graph.process_instructions(&robot, &instructions);
+        let (graph, robot, stats) = graph.simulate_with_stats(&robot, &instructions);
+
+        assert_eq!(stats.moved + stats.blocked, instructions.len());
+        assert_eq!(
+            (stats.moved, stats.blocked, stats.boxes_pushed),
+            (10, 5, 10)
+        );
+        // Stats shouldn't change the outcome of the simulation.
+        assert_eq!(graph.normalize(), graph_exp.normalize());
+        assert_eq!(robot, robot_exp);
+    }
+
+    #[test]
+    fn test_run_until_stable() {
+        let input = r"
+            #####
+            #...#
+            #.@.#
+            #...#
+            #####
+
+            <^<^<^<^
+        ";
+        let (graph, instructions, robot) = parse_input(input, false).unwrap();
+        let (_, robot, stable_from) = graph.run_until_stable(&robot, &instructions);
+
+        // Wedged into the top left corner after two moves, the rest just push into the walls.
+        assert_eq!(robot.position(), Point::new(1, 1));
+        assert_eq!(stable_from, 2);
+        assert!(stable_from < instructions.len());
+
+        // The last instruction of the large sample still moves the robot, so it never stabilises.
+        let (graph, instructions, robot) = parse_input(LARGE_INPUT, false).unwrap();
+        let (graph_exp, robot_exp) = graph.process_instructions(&robot, &instructions);
+        let (graph, robot, stable_from) = graph.run_until_stable(&robot, &instructions);
+        assert_eq!(graph.normalize(), graph_exp.normalize());
+        assert_eq!(robot, robot_exp);
+        assert_eq!(stable_from, instructions.len());
+    }
+
     #[test]
     fn test_solve_part_1() {
         let expected = 2028;
@@ -1,9 +1,13 @@
 use anyhow::Result;
+use common::read_input;
 use image::{ImageBuffer, Rgb};
 use priority_queue::PriorityQueue;
+use rayon::prelude::*;
 use std::{
     cmp::Reverse,
     collections::{HashMap, HashSet, VecDeque, hash_map::Entry},
+    str::FromStr,
+    time::{Duration, Instant},
 };
 use thiserror::Error;
 
@@ -24,6 +28,8 @@ pub enum GraphError {
     PointNotFound(Point),
     #[error("Backtracking failed")]
     BacktrackingFailed,
+    #[error("Invalid weight {1} at {0}, must be 1 to 9")]
+    InvalidWeight(Point, i32),
 }
 
 // Represents a 2d point in the graph.
@@ -48,10 +54,22 @@ impl Point {
         }
     }
 
+    /// Returns the direction of a neighbouring point, or None if the points aren't neighbours.
+    pub fn direction_to(&self, other: Point) -> Option<Direction> {
+        Direction::all()
+            .into_iter()
+            .find(|&direction| self.neighbour(direction) == other)
+    }
+
     /// Returns the manhattan distance between two points.
     pub fn distance(&self, other: Point) -> i32 {
         (self.x - other.x).abs() + (self.y - other.y).abs()
     }
+
+    /// Returns true if the point lies inside a grid of the given size.
+    pub fn in_bounds(&self, width: i32, height: i32) -> bool {
+        self.x >= 0 && self.x < width && self.y >= 0 && self.y < height
+    }
 }
 
 impl std::fmt::Display for Point {
@@ -69,6 +87,28 @@ pub enum Direction {
 }
 
 impl Direction {
+    /// All four directions, in declaration order.
+    pub fn all() -> [Self; 4] {
+        [
+            Direction::North,
+            Direction::South,
+            Direction::East,
+            Direction::West,
+        ]
+    }
+
+    /// Maps a compass letter (`N`, `S`, `E`, `W`) or arrow (`^`, `v`, `>`, `<`) to its direction.
+    /// The maze itself has no direction characters, so `parse_input` never calls this; it's for library callers.
+    pub fn from_char(c: char) -> Option<Direction> {
+        match c {
+            'N' | '^' => Some(Direction::North),
+            'S' | 'v' => Some(Direction::South),
+            'E' | '>' => Some(Direction::East),
+            'W' | '<' => Some(Direction::West),
+            _ => None,
+        }
+    }
+
     /// Gets the direction opposite to the current direction.
     pub fn opposite(&self) -> Self {
         match self {
@@ -97,14 +137,27 @@ pub struct Graph {
     nodes: HashMap<Point, Node>,
     width: i32,
     height: i32,
+    start: Point,
+    end: Point,
 }
 
 impl Graph {
-    /// Creates a new graph from a HashSet of points.
+    /// Creates a new graph from a map of points to their entry weights, along with the start and end points of the maze.
     /// Assumes that the points are connected in a grid-like manner.
-    pub fn new(pos_map: HashSet<Point>, width: i32, height: i32) -> Self {
+    /// Weights must be between 1 and 9, as anything lower would break the search's cost bookkeeping.
+    pub fn new(
+        pos_map: HashMap<Point, i32>,
+        width: i32,
+        height: i32,
+        start: Point,
+        end: Point,
+    ) -> Result<Self, GraphError> {
         let mut nodes = HashMap::new();
-        for point in &pos_map {
+        for (point, &weight) in &pos_map {
+            if !(1..=9).contains(&weight) {
+                return Err(GraphError::InvalidWeight(*point, weight));
+            }
+
             // Calculate all the possible neighbour points.
             let north = point.neighbour(Direction::North);
             let south = point.neighbour(Direction::South);
@@ -112,7 +165,8 @@ impl Graph {
             let west = point.neighbour(Direction::West);
 
             // Helper to map a point to Option if it is a valid point in the graph.
-            let get_direction = |point: Point| -> Option<Point> { pos_map.get(&point).copied() };
+            let get_direction =
+                |point: Point| -> Option<Point> { pos_map.contains_key(&point).then_some(point) };
 
             // Create a new node.
             // For each neighbour, check if it is a valid point in the graph.
@@ -121,15 +175,34 @@ impl Graph {
                 south: get_direction(south),
                 east: get_direction(east),
                 west: get_direction(west),
+                weight,
             };
 
             nodes.insert(*point, node);
         }
-        Self {
+        Ok(Self {
             nodes,
             width,
             height,
-        }
+            start,
+            end,
+        })
+    }
+
+    /// The start point of the maze.
+    pub fn start(&self) -> Point {
+        self.start
+    }
+
+    /// The end point of the maze.
+    pub fn end(&self) -> Point {
+        self.end
+    }
+
+    /// The extra cost of entering the given point, on top of the straight or turn cost.
+    /// A tile with weight 1 (the default) costs nothing extra.
+    fn entry_penalty(&self, point: Point) -> i32 {
+        self.nodes.get(&point).map_or(0, |node| node.weight - 1)
     }
 
     /// Gets a node at the given point.
@@ -141,15 +214,134 @@ impl Graph {
             .ok_or(GraphError::PointNotFound(*point))
     }
 
+    /// Finds all open cells which can be reached from the given point, ignoring headings and costs.
+    pub fn component(&self, p: Point) -> Result<HashSet<Point>, GraphError> {
+        self.get(&p)?; // Check if the point is valid.
+        let mut reachable = HashSet::from([p]);
+        let mut queue = VecDeque::from([p]);
+
+        while let Some(current) = queue.pop_front() {
+            for next in self.neighbours(current) {
+                if reachable.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        Ok(reachable)
+    }
+
+    /// Returns the open cells directly connected to the given point, ignoring headings and costs.
+    /// Empty if the point isn't an open cell.
+    pub fn neighbours(&self, p: Point) -> Vec<Point> {
+        let Some(node) = self.nodes.get(&p) else {
+            return Vec::new();
+        };
+
+        Direction::all()
+            .into_iter()
+            .filter_map(|direction| node.neighbour(direction))
+            .collect()
+    }
+
+    /// Finds all open cells which can't be reached from the start point, ignoring headings and costs.
+    /// Useful for spotting sealed off pockets in malformed mazes.
+    pub fn unreachable_from(&self, start: Point) -> HashSet<Point> {
+        // An invalid start can't reach anything.
+        let reachable = self.component(start).unwrap_or_default();
+
+        self.nodes
+            .keys()
+            .filter(|point| !reachable.contains(point))
+            .copied()
+            .collect()
+    }
+
     /// Uses a modiiied A* algorithm to find all shortest paths from start to end.
-    /// Returns a vector of paths.
+    /// Returns the paths along with their cost.
     /// Heavily inspired by the `astar_bag` function in the `pathfinding` crate.
     /// https://github.com/evenfurther/pathfinding/blob/main/src/directed/astar.rs#L173
-    fn astar_all_paths(&self, start: Point, end: Point) -> Result<(Vec<Path>, i32), GraphError> {
+    fn astar_all_paths(&self, start: Point, end: Point) -> Result<Solution, GraphError> {
+        let (backtrace, min_cost) = self.astar_backtrace(start, end)?;
+        if start == end {
+            return Ok(Solution::new(vec![Path::new(vec![start])], min_cost));
+        }
+
+        // BACKTRACKING
+        let mut all_paths = Vec::new();
+        let mut stack = VecDeque::new();
+        stack.push_back((end, (vec![end], min_cost)));
+        while let Some((point, (path, cost))) = stack.pop_front() {
+            if point == start {
+                all_paths.push(Path::new(path));
+                continue;
+            }
+            // The last point in the path is the one we stepped back from.
+            let child = path[path.len().saturating_sub(2)];
+            for (p, c) in self.best_parents(&backtrace, point, cost, child)? {
+                let mut new_path = path.clone();
+                new_path.push(p);
+                stack.push_back((p, (new_path, c)));
+            }
+        }
+        // The backtrace is built from hash maps, so sort the paths to keep equal cost ones in a stable order.
+        all_paths.sort();
+        Ok(Solution::new(all_paths, min_cost))
+    }
+
+    /// Finds the parents of a point that are one step back along a shortest path, while backtracking.
+    /// The cost carried to `point` is what it cost to reach `child`, the point one step closer to the end,
+    /// so the step being checked is the one onto `child` and has to include its entry penalty.
+    fn best_parents(
+        &self,
+        backtrace: &Backtrace,
+        point: Point,
+        cost: i32,
+        child: Point,
+    ) -> Result<Vec<(Point, i32)>, GraphError> {
+        let ps = backtrace
+            .get(&point)
+            .filter(|ps| !ps.is_empty())
+            .ok_or(GraphError::BacktrackingFailed)?; // Should never happen.
+        let penalty = self.entry_penalty(child);
+
+        Ok(ps
+            .iter()
+            .filter_map(|(p, c)| p.map(|p| (p, *c)))
+            .filter(|(_, c)| is_step(cost - c, penalty))
+            .collect())
+    }
+
+    /// Finds every tile on any shortest path from start to end, along with the cost of those paths.
+    /// Same result as the union of the paths from `astar_all_paths`, but without building the paths.
+    pub fn best_path_tiles(
+        &self,
+        start: Point,
+        end: Point,
+    ) -> Result<(HashSet<Point>, i32), GraphError> {
+        let (backtrace, min_cost) = self.astar_backtrace(start, end)?;
+        let mut tiles = HashSet::new();
+        let mut memo = HashMap::new();
+        collect_best_tiles(
+            self,
+            &backtrace,
+            start,
+            (end, end),
+            min_cost,
+            &mut memo,
+            &mut tiles,
+        )?;
+
+        Ok((tiles, min_cost))
+    }
+
+    /// Runs the A* search from start to end, returning the parents of every explored point and the minimum cost.
+    /// The parents map each point to the cost it was reached at from each of its parent points.
+    fn astar_backtrace(&self, start: Point, end: Point) -> Result<(Backtrace, i32), GraphError> {
         self.get(&start)?; // Check if the start point is valid.
         self.get(&end)?; // Check if the end point is valid.
         if start == end {
-            return Ok((vec![Path::new(vec![start])], 0));
+            return Ok((HashMap::new(), 0));
         }
         let initial_heading = Direction::East; // Always true, per the problem statement.
         let mut frontier = PriorityQueue::new();
@@ -176,7 +368,7 @@ impl Graph {
                 .iter()
                 .filter_map(|n| n.0.map(|point| (point, n.1, n.2)))
             {
-                let new_cost = parent_cost + new_cost; // New cost to reach the next point.
+                let new_cost = parent_cost + new_cost + self.entry_penalty(next); // New cost to reach the next point.
                 let h = next.distance(end); // Heuristic cost.
                 match parents.entry((next, direction)) {
                     Entry::Vacant(e) => {
@@ -192,93 +384,209 @@ impl Graph {
         }
         let min_cost = min_cost.ok_or(GraphError::NoPathFound)?; // If no path was found, return an error.
 
-        // BACKTRACKING
-        let mut all_paths = Vec::new();
-        let mut backtrace: HashMap<Point, HashMap<Option<Point>, i32>> = HashMap::new();
+        let mut backtrace: Backtrace = HashMap::new();
         for ((point, _), (parent, cost)) in parents.iter() {
             backtrace
                 .entry(*point)
                 .or_default() // Create a new HashMap if the point is not in the map.
                 .insert(parent.map(|(p, _)| p), *cost); // Insert the parent and cost.
         }
-        let mut stack = VecDeque::new();
-        stack.push_back((end, (vec![end], min_cost)));
-        while let Some((point, (path, cost))) = stack.pop_front() {
-            if point == start {
-                all_paths.push(Path::new(path));
-                continue;
-            }
-            let ps = backtrace.get(&point).unwrap();
-            if ps.is_empty() {
-                return Err(GraphError::BacktrackingFailed); // Should never happen.
+
+        Ok((backtrace, min_cost))
+    }
+
+    /// Finds the cost of the cheapest path from start to end, without tracking the paths themselves.
+    /// Plain A* that stops as soon as the end is reached, so it's faster than `astar_all_paths` for part 1.
+    pub fn shortest_cost(&self, start: Point, end: Point) -> Result<i32, GraphError> {
+        self.get(&start)?; // Check if the start point is valid.
+        self.get(&end)?; // Check if the end point is valid.
+        let initial_heading = Direction::East; // Always true, per the problem statement.
+        let mut frontier = PriorityQueue::new();
+        let mut costs = HashMap::new(); // Cheapest known cost to reach each (point, heading).
+        frontier.push((start, initial_heading), Reverse(start.distance(end))); // Reversed for min heap.
+        costs.insert((start, initial_heading), 0);
+
+        while let Some(((current, heading), _)) = frontier.pop() {
+            let cost = costs[&(current, heading)];
+            if current == end {
+                return Ok(cost); // The heuristic never overestimates, so the first arrival is the cheapest.
             }
-            for (p, c) in ps.iter() {
-                let p = p.unwrap();
-                // 0 is a hack, it's only needed for the first step.
-                if matches!(cost - c, 0 | STRAIGHT_COST | TURN_COST) {
-                    let mut new_path = path.clone();
-                    new_path.push(p);
-                    stack.push_back((p, (new_path, *c)));
+            let node = self.get(&current)?;
+
+            for (next, direction, step_cost) in node
+                .get_neighbours(heading)
+                .iter()
+                .filter_map(|n| n.0.map(|point| (point, n.1, n.2)))
+            {
+                let new_cost = cost + step_cost + self.entry_penalty(next);
+                if costs
+                    .get(&(next, direction))
+                    .is_some_and(|&known| known <= new_cost)
+                {
+                    continue; // Already reached this way at a lower cost.
                 }
+                costs.insert((next, direction), new_cost);
+                let h = next.distance(end); // Heuristic cost.
+                frontier.push_increase((next, direction), Reverse(new_cost + h));
             }
         }
-        Ok((all_paths, min_cost))
+
+        Err(GraphError::NoPathFound)
+    }
+
+    /// Finds the start with the cheapest path to the end, along with that cost.
+    /// Each start is searched in parallel with `shortest_cost`. Starts that can't reach the end are skipped,
+    /// so this only fails if none of them can. Ties go to the earliest start in the slice.
+    pub fn best_from_starts(
+        &self,
+        starts: &[Point],
+        end: Point,
+    ) -> Result<(Point, i32), GraphError> {
+        starts
+            .par_iter()
+            .filter_map(|&start| {
+                self.shortest_cost(start, end)
+                    .ok()
+                    .map(|cost| (start, cost))
+            })
+            .min_by_key(|&(_, cost)| cost)
+            .ok_or(GraphError::NoPathFound)
+    }
+
+    /// Runs `astar_all_paths` the given number of times (at least once) and returns the median duration.
+    pub fn benchmark(&self, start: Point, end: Point, runs: usize) -> Result<Duration, GraphError> {
+        let mut durations = Vec::with_capacity(runs.max(1));
+        for _ in 0..runs.max(1) {
+            let start_time = Instant::now();
+            self.astar_all_paths(start, end)?;
+            durations.push(start_time.elapsed());
+        }
+        durations.sort_unstable();
+
+        Ok(durations[durations.len() / 2])
+    }
+
+    /// Finds the smallest box containing every open cell, as its top left and bottom right corners.
+    /// Useful for skipping the wall margin around mazes embedded in a larger grid.
+    pub fn bounding_box(&self) -> (Point, Point) {
+        // There's always at least a start and an end, so the box is never empty.
+        self.nodes.keys().fold(
+            (
+                Point::new(i32::MAX, i32::MAX),
+                Point::new(i32::MIN, i32::MIN),
+            ),
+            |(min, max), p| {
+                (
+                    Point::new(min.x.min(p.x), min.y.min(p.y)),
+                    Point::new(max.x.max(p.x), max.y.max(p.y)),
+                )
+            },
+        )
     }
 
     /// Helper function that prints a graph along with every node in a path.
     /// Needs to be told the size since the graph is not stored as a 2D array.
     pub fn draw(&self, paths: &HashSet<Point>) {
-        for y in 0..self.height {
-            for x in 0..self.width {
+        let corner = Point::new(self.width - 1, self.height - 1);
+        print!("{}", self.draw_region(paths, Point::new(0, 0), corner));
+    }
+
+    /// Same as `draw`, but only prints the bounding box of the open cells.
+    pub fn draw_cropped(&self, paths: &HashSet<Point>) {
+        let (min, max) = self.bounding_box();
+        print!("{}", self.draw_region(paths, min, max));
+    }
+
+    /// Draws the graph between the two corners, inclusive, followed by a blank line.
+    fn draw_region(&self, paths: &HashSet<Point>, min: Point, max: Point) -> String {
+        let mut output = String::new();
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
                 let point = Point::new(x, y);
-                if paths.contains(&point) {
-                    print!("O");
+                if !point.in_bounds(self.width, self.height) {
+                    // Anything outside the maze is treated as wall, even if a path claims it.
+                    output.push('$');
+                } else if paths.contains(&point) {
+                    output.push('O');
                 } else if self.nodes.contains_key(&point) {
-                    print!("·"); // Middle Dot not a period.
+                    output.push('·'); // Middle Dot not a period.
                 } else {
-                    print!("$");
+                    output.push('$');
                 }
             }
-            println!();
+            output.push('\n');
         }
-        println!();
+        output.push('\n');
+        output
     }
 
     /// Just for fun, draw the graph to a BMP file.
     pub fn save_bmp(&self, paths: &HashSet<Point>, filename: &str) -> Result<()> {
-        let width = self.width as u32;
-        let height = self.height as u32;
+        let corner = Point::new(self.width - 1, self.height - 1);
+        save_image(
+            &self.render_region(paths, Point::new(0, 0), corner),
+            filename,
+        )
+    }
+
+    /// Same as `save_bmp`, but only draws the bounding box of the open cells, so the image is smaller.
+    pub fn save_bmp_cropped(&self, paths: &HashSet<Point>, filename: &str) -> Result<()> {
+        let (min, max) = self.bounding_box();
+        save_image(&self.render_region(paths, min, max), filename)
+    }
+
+    /// Draws the graph between the two corners, inclusive, with one pixel per tile.
+    fn render_region(
+        &self,
+        paths: &HashSet<Point>,
+        min: Point,
+        max: Point,
+    ) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        let width = (max.x - min.x + 1) as u32;
+        let height = (max.y - min.y + 1) as u32;
         let mut img = ImageBuffer::new(width, height);
 
-        for y in 0..height {
-            for x in 0..width {
-                let point = Point::new(x as i32, y as i32);
-                let pixel = if paths.contains(&point) {
-                    Rgb([255u8, 0, 0]) // Red for 'O'
-                } else if self.nodes.contains_key(&point) {
-                    if (x + y) % 2 == 0 {
-                        Rgb([255u8, 255, 255]) // White for '·' on even tiles
-                    } else {
-                        Rgb([200u8, 200, 200]) // Light gray for '·' on odd tiles
-                    }
+        for (x, y, pixel) in img.enumerate_pixels_mut() {
+            let point = Point::new(min.x + x as i32, min.y + y as i32);
+            *pixel = if self.nodes.contains_key(&point) {
+                // The checkerboard follows the maze, not the image, so crops line up.
+                if (point.x + point.y) % 2 == 0 {
+                    Rgb([255u8, 255, 255]) // White for '·' on even tiles
                 } else {
-                    Rgb([0u8, 0, 0]) // Black for '$'
-                };
-                img.put_pixel(x, y, pixel);
-            }
+                    Rgb([200u8, 200, 200]) // Light gray for '·' on odd tiles
+                }
+            } else {
+                Rgb([0u8, 0, 0]) // Black for '$'
+            };
         }
 
-        // Create the necessary directories
-        if let Some(parent) = std::path::Path::new(filename).parent() {
-            std::fs::create_dir_all(parent)?;
+        // Paths go on top, skipping any points that would fall outside the maze or the region.
+        for point in paths.iter().filter(|p| {
+            p.in_bounds(self.width, self.height)
+                && (min.x..=max.x).contains(&p.x)
+                && (min.y..=max.y).contains(&p.y)
+        }) {
+            let (x, y) = ((point.x - min.x) as u32, (point.y - min.y) as u32);
+            img.put_pixel(x, y, Rgb([255u8, 0, 0])); // Red for 'O'
         }
 
-        img.save(filename)?;
-        Ok(())
+        img
     }
 }
 
-#[derive(Debug, Default, Clone)]
+/// Saves an image, creating any missing directories.
+fn save_image(img: &ImageBuffer<Rgb<u8>, Vec<u8>>, filename: &str) -> Result<()> {
+    // Create the necessary directories
+    if let Some(parent) = std::path::Path::new(filename).parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    img.save(filename)?;
+    Ok(())
+}
+
+/// Paths order lexicographically by their points, in the order they were walked.
+#[derive(Debug, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Path {
     points: Vec<Point>,
 }
@@ -296,6 +604,52 @@ impl Path {
     pub fn to_set(&self) -> HashSet<Point> {
         self.points.iter().copied().collect()
     }
+
+    /// Returns the endpoints and every point where the path changes direction, in path order.
+    pub fn corners(&self) -> Vec<Point> {
+        let (Some(&first), Some(&last)) = (self.points.first(), self.points.last()) else {
+            return Vec::new();
+        };
+        if self.points.len() == 1 {
+            return vec![first];
+        }
+
+        let mut corners = vec![first];
+        for window in self.points.windows(3) {
+            let (before, point, after) = (window[0], window[1], window[2]);
+            if before.direction_to(point) != point.direction_to(after) {
+                corners.push(point);
+            }
+        }
+        corners.push(last);
+        corners
+    }
+}
+
+/// Every shortest path between two points, along with their shared cost.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Solution {
+    paths: Vec<Path>,
+    cost: i32,
+}
+
+impl Solution {
+    pub fn new(paths: Vec<Path>, cost: i32) -> Self {
+        Self { paths, cost }
+    }
+
+    pub fn cost(&self) -> i32 {
+        self.cost
+    }
+
+    pub fn paths(&self) -> &[Path] {
+        &self.paths
+    }
+
+    /// Every point that lies on at least one of the paths.
+    pub fn unique_tiles(&self) -> HashSet<Point> {
+        unique_points_in_paths(&self.paths)
+    }
 }
 
 /// A node in the graph.
@@ -306,6 +660,8 @@ pub struct Node {
     south: Option<Point>,
     east: Option<Point>,
     west: Option<Point>,
+    /// Entry weight of this tile, 1 for a normal tile and up to 9 for heavy mud.
+    weight: i32,
 }
 
 impl Node {
@@ -330,17 +686,17 @@ impl Node {
 }
 
 fn main() -> Result<()> {
-    let input = std::fs::read_to_string("input.txt")?;
+    let input = read_input(std::env::args().nth(1))?;
     let (graph, start, end) = parse_input(&input)?;
     // Pathfinding benchmarking.
-    let start_time = std::time::Instant::now();
-    let (paths, cost) = graph.astar_all_paths(start, end)?;
+    let start_time = Instant::now();
+    let solution = graph.astar_all_paths(start, end)?;
     let elapsed = start_time.elapsed();
     println!("Pathfinding completed in {}ms", elapsed.as_millis());
 
-    println!("Part 1: {}", cost);
+    println!("Part 1: {}", solution.cost());
 
-    let unique = unique_points_in_paths(&paths);
+    let unique = solution.unique_tiles();
     println!("Part 2: {}", unique.len());
 
     // Prints the graph as a bmp file.
@@ -349,6 +705,51 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+/// Maps each point to the cost it was reached at from each of its parent points.
+type Backtrace = HashMap<Point, HashMap<Option<Point>, i32>>;
+
+/// Walks the backtrace from the given point towards the start, following the same steps as the
+/// backtracking in `astar_all_paths`. Adds every point that lies on a walk to the start to `tiles`.
+/// The step is the point and the child it was stepped back from, as in `Graph::best_parents`.
+/// Returns whether the start can be reached, memoized per (point, child, cost) so shared steps are only walked once.
+fn collect_best_tiles(
+    graph: &Graph,
+    backtrace: &Backtrace,
+    start: Point,
+    (point, child): (Point, Point),
+    cost: i32,
+    memo: &mut HashMap<(Point, Point, i32), bool>,
+    tiles: &mut HashSet<Point>,
+) -> Result<bool, GraphError> {
+    if let Some(&reaches_start) = memo.get(&(point, child, cost)) {
+        return Ok(reaches_start);
+    }
+    if point == start {
+        tiles.insert(point);
+        memo.insert((point, child, cost), true);
+        return Ok(true);
+    }
+    // Mark as a dead end while exploring, so that zero cost loops can't recurse forever.
+    memo.insert((point, child, cost), false);
+
+    let mut reaches_start = false;
+    for (p, c) in graph.best_parents(backtrace, point, cost, child)? {
+        reaches_start |= collect_best_tiles(graph, backtrace, start, (p, point), c, memo, tiles)?;
+    }
+
+    if reaches_start {
+        tiles.insert(point);
+    }
+    memo.insert((point, child, cost), reaches_start);
+    Ok(reaches_start)
+}
+
+/// Checks whether a difference in cost is a single move onto a tile with the given entry penalty.
+fn is_step(cost_difference: i32, penalty: i32) -> bool {
+    // 0 is a hack, it's only needed for the first step.
+    cost_difference == 0 || matches!(cost_difference - penalty, STRAIGHT_COST | TURN_COST)
+}
+
 fn unique_points_in_paths(paths: &[Path]) -> HashSet<Point> {
     paths
         .iter()
@@ -359,7 +760,7 @@ fn unique_points_in_paths(paths: &[Path]) -> HashSet<Point> {
 pub fn parse_input(input: &str) -> Result<(Graph, Point, Point), GraphError> {
     // Start by normalizing line endings to \n.
     let s = input.replace("\r\n", "\n");
-    let mut pos_map = HashSet::new();
+    let mut pos_map = HashMap::new();
     let mut start = None;
     let mut end = None;
     let mut width = 0;
@@ -373,16 +774,20 @@ pub fn parse_input(input: &str) -> Result<(Graph, Point, Point), GraphError> {
             match c {
                 // Empty Space
                 '.' => {
-                    pos_map.insert(point);
+                    pos_map.insert(point, 1);
+                }
+                // Mud, costs extra to enter
+                '1'..='9' => {
+                    pos_map.insert(point, c.to_digit(10).unwrap() as i32);
                 }
                 // Start Point
                 'S' => {
-                    pos_map.insert(point);
+                    pos_map.insert(point, 1);
                     start = Some(point);
                 }
                 // End Point
                 'E' => {
-                    pos_map.insert(point);
+                    pos_map.insert(point, 1);
                     end = Some(point);
                 }
                 // Do nothing for walls.
@@ -395,12 +800,21 @@ pub fn parse_input(input: &str) -> Result<(Graph, Point, Point), GraphError> {
     let start = start.ok_or(GraphError::MissingStart)?;
     let end = end.ok_or(GraphError::MissingEnd)?;
     Ok((
-        Graph::new(pos_map, (width + 1) as i32, (height + 1) as i32),
+        Graph::new(pos_map, (width + 1) as i32, (height + 1) as i32, start, end)?,
         start,
         end,
     ))
 }
 
+impl FromStr for Graph {
+    type Err = GraphError;
+
+    /// Parses the maze, keeping the start and end points in the graph.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_input(s).map(|(graph, _, _)| graph)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -443,6 +857,34 @@ mod test {
         #################
 ";
 
+    #[test]
+    fn test_direction_from_char() {
+        assert_eq!(Direction::from_char('N'), Some(Direction::North));
+        assert_eq!(Direction::from_char('^'), Some(Direction::North));
+        assert_eq!(Direction::from_char('S'), Some(Direction::South));
+        assert_eq!(Direction::from_char('v'), Some(Direction::South));
+        assert_eq!(Direction::from_char('E'), Some(Direction::East));
+        assert_eq!(Direction::from_char('>'), Some(Direction::East));
+        assert_eq!(Direction::from_char('W'), Some(Direction::West));
+        assert_eq!(Direction::from_char('<'), Some(Direction::West));
+        assert_eq!(Direction::from_char('n'), None);
+        assert_eq!(Direction::from_char('.'), None);
+    }
+
+    #[test]
+    fn test_direction_all() {
+        let all = Direction::all();
+        let unique: HashSet<Direction> = all.into_iter().collect();
+        assert_eq!(unique.len(), 4);
+
+        // One step each way ends up back where it started.
+        let origin = Point::new(3, 3);
+        let end = all
+            .iter()
+            .fold(origin, |p, &direction| p.neighbour(direction));
+        assert_eq!(end, origin);
+    }
+
     #[test]
     fn test_parse_input() {
         let (graph, start, end) = parse_input(INPUT_ONE).unwrap();
@@ -460,15 +902,348 @@ mod test {
         let p2 = g2.astar_all_paths(s2, e2).expect("Expected a path");
 
         // Check that the number of paths are correct.
-        assert_eq!(p1.0.len(), 3, "Expected {} paths, got {}", 3, p1.0.len());
-        assert_eq!(p2.0.len(), 2, "Expected {} paths, got {}", 3, p2.0.len());
+        let (n1, n2) = (p1.paths().len(), p2.paths().len());
+        assert_eq!(n1, 3, "Expected {} paths, got {}", 3, n1);
+        assert_eq!(n2, 2, "Expected {} paths, got {}", 3, n2);
         // Check that the cost of the shortest path is correct.
-        assert_eq!(p1.1, 7036, "Expected cost {}, got {}", 7036, p1.1);
-        assert_eq!(p2.1, 11048, "Expected cost {}, got {}", 11048, p2.1);
+        assert_eq!(p1.cost(), 7036, "Expected cost {}, got {}", 7036, p1.cost());
+        assert_eq!(
+            p2.cost(),
+            11048,
+            "Expected cost {}, got {}",
+            11048,
+            p2.cost()
+        );
         // Check that the number of unique points in the paths are correct.
-        let u1 = unique_points_in_paths(&p1.0);
-        let u2 = unique_points_in_paths(&p2.0);
+        let u1 = unique_points_in_paths(p1.paths());
+        let u2 = unique_points_in_paths(p2.paths());
         assert_eq!(u1.len(), 45, "Expected {} points, got {}", 45, u1.len());
         assert_eq!(u2.len(), 64, "Expected {} points, got {}", 64, u2.len());
     }
+
+    #[test]
+    fn test_solution_unique_tiles() {
+        for input in [INPUT_ONE, INPUT_TWO] {
+            let (graph, start, end) = parse_input(input).unwrap();
+            let solution = graph.astar_all_paths(start, end).unwrap();
+
+            assert_eq!(
+                solution.unique_tiles().len(),
+                unique_points_in_paths(solution.paths()).len()
+            );
+        }
+    }
+
+    #[test]
+    fn test_benchmark() {
+        let (graph, start, end) = parse_input(INPUT_ONE).unwrap();
+
+        assert!(graph.benchmark(start, end, 3).unwrap() > Duration::ZERO);
+        // Zero runs still runs once
+        assert!(graph.benchmark(start, end, 0).unwrap() > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_unreachable_from() {
+        let input = r"
+            ########
+            #S.E#..#
+            ########
+        ";
+        let (graph, start, _) = parse_input(input).unwrap();
+
+        let expected = HashSet::from([Point::new(5, 1), Point::new(6, 1)]);
+        assert_eq!(graph.unreachable_from(start), expected);
+
+        let (graph, start, _) = parse_input(INPUT_ONE).unwrap();
+        assert!(graph.unreachable_from(start).is_empty());
+    }
+
+    #[test]
+    fn test_best_path_tiles() {
+        let (g1, s1, e1) = parse_input(INPUT_ONE).unwrap();
+        let (g2, s2, e2) = parse_input(INPUT_TWO).unwrap();
+
+        let (t1, c1) = g1.best_path_tiles(s1, e1).unwrap();
+        let (t2, c2) = g2.best_path_tiles(s2, e2).unwrap();
+
+        assert_eq!((t1.len(), c1), (45, 7036));
+        assert_eq!((t2.len(), c2), (64, 11048));
+        assert_eq!(t1, g1.astar_all_paths(s1, e1).unwrap().unique_tiles());
+        assert_eq!(t2, g2.astar_all_paths(s2, e2).unwrap().unique_tiles());
+    }
+
+    #[test]
+    fn test_component() {
+        let (graph, start, end) = parse_input(INPUT_ONE).unwrap();
+        let component = graph.component(start).unwrap();
+
+        assert_eq!(component.len(), 104);
+        assert!(component.contains(&end));
+        assert!(matches!(
+            graph.component(Point::new(0, 0)),
+            Err(GraphError::PointNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_shortest_cost() {
+        for input in [INPUT_ONE, INPUT_TWO] {
+            let (graph, start, end) = parse_input(input).unwrap();
+            let expected = graph.astar_all_paths(start, end).unwrap().cost();
+
+            assert_eq!(graph.shortest_cost(start, end).unwrap(), expected);
+        }
+
+        let (graph, start, end) = parse_input(INPUT_ONE).unwrap();
+        assert_eq!(graph.shortest_cost(start, end).unwrap(), 7036);
+        assert_eq!(graph.shortest_cost(start, start).unwrap(), 0);
+        let (graph, start, end) = parse_input(INPUT_TWO).unwrap();
+        assert_eq!(graph.shortest_cost(start, end).unwrap(), 11048);
+    }
+
+    #[test]
+    fn test_neighbours() {
+        let (graph, start, _) = parse_input(INPUT_ONE).unwrap();
+
+        // A corridor along the top row.
+        let corridor = graph.neighbours(Point::new(2, 1));
+        assert_eq!(corridor, vec![Point::new(3, 1), Point::new(1, 1)]);
+        // The top row branches south here.
+        let junction = graph.neighbours(Point::new(3, 1));
+        assert_eq!(junction.len(), 3);
+        assert!(junction.contains(&Point::new(3, 2)));
+
+        assert_eq!(graph.neighbours(start).len(), 2);
+        assert!(graph.neighbours(Point::new(0, 0)).is_empty());
+    }
+
+    #[test]
+    fn test_path_corners() {
+        // Three steps east, then two north.
+        let points = [(1, 3), (2, 3), (3, 3), (4, 3), (4, 2), (4, 1)];
+        let path = Path::new(points.iter().map(|&(x, y)| Point::new(x, y)).collect());
+        assert_eq!(
+            path.corners(),
+            vec![Point::new(1, 3), Point::new(4, 3), Point::new(4, 1)]
+        );
+
+        assert_eq!(
+            Path::new(vec![Point::new(1, 1)]).corners(),
+            vec![Point::new(1, 1)]
+        );
+        assert!(Path::default().corners().is_empty());
+        assert_eq!(
+            Point::new(4, 3).direction_to(Point::new(4, 2)),
+            Some(Direction::North)
+        );
+        assert_eq!(Point::new(4, 3).direction_to(Point::new(5, 2)), None);
+
+        // 7036 is 7 turns and 36 steps, so the best paths bend at most 7 times between the endpoints.
+        let (graph, start, end) = parse_input(INPUT_ONE).unwrap();
+        let solution = graph.astar_all_paths(start, end).unwrap();
+        assert!(
+            solution
+                .paths()
+                .iter()
+                .all(|path| path.corners().len() <= 9)
+        );
+    }
+
+    #[test]
+    fn test_astar_all_paths_order() {
+        let (graph, start, end) = parse_input(INPUT_ONE).unwrap();
+        let first = graph.astar_all_paths(start, end).unwrap().paths().to_vec();
+        let second = graph.astar_all_paths(start, end).unwrap().paths().to_vec();
+
+        assert!(first.len() > 1);
+        assert_eq!(first, second);
+        assert!(first.windows(2).all(|pair| pair[0] <= pair[1]));
+
+        // A freshly parsed graph has different hash maps, but the order is still the same.
+        let (graph, start, end) = parse_input(INPUT_ONE).unwrap();
+        assert_eq!(graph.astar_all_paths(start, end).unwrap().paths(), first);
+    }
+
+    #[test]
+    fn test_bounding_box() {
+        let input = "\
+            ###########
+            ###########
+            ###S...####
+            ###.##.####
+            ###....E###
+            ###########
+            ###########";
+        let (graph, _, _) = parse_input(input).unwrap();
+        let (min, max) = graph.bounding_box();
+
+        assert_eq!((min, max), (Point::new(3, 2), Point::new(7, 4)));
+        assert_eq!(
+            graph.draw_region(&HashSet::from([min]), min, max),
+            "O···$\n·$$·$\n·····\n\n"
+        );
+
+        // The cropped image is the size of the box, and starts at its top left corner
+        let img = graph.render_region(&HashSet::from([min]), min, max);
+        assert_eq!(img.dimensions(), (5, 3));
+        assert_eq!(*img.get_pixel(0, 0), Rgb([255, 0, 0]));
+        assert_eq!(*img.get_pixel(4, 0), Rgb([0, 0, 0]));
+        assert_eq!(*img.get_pixel(1, 0), Rgb([255, 255, 255]));
+        assert_eq!(*img.get_pixel(2, 0), Rgb([200, 200, 200]));
+    }
+
+    #[test]
+    fn test_point_in_bounds() {
+        let input = "\
+            #####
+            #S.E#
+            #####";
+        let (graph, _, _) = parse_input(input).unwrap();
+        let (width, height) = (graph.width, graph.height);
+
+        assert!(Point::new(0, 0).in_bounds(width, height));
+        assert!(Point::new(4, 2).in_bounds(width, height));
+        assert!(!Point::new(5, 2).in_bounds(width, height));
+        assert!(!Point::new(4, 3).in_bounds(width, height));
+        assert!(!Point::new(-1, 0).in_bounds(width, height));
+
+        // A stray path point outside the maze is drawn as wall
+        let stray = HashSet::from([Point::new(5, 1)]);
+        assert_eq!(
+            graph.draw_region(&stray, Point::new(3, 1), Point::new(5, 1)),
+            "·$$\n\n"
+        );
+    }
+
+    #[test]
+    fn test_best_from_starts() {
+        // (5, 3) is closer to the end but has to turn, (3, 3) is walled in.
+        let input = "\
+            #######
+            #S...E#
+            #.###.#
+            #.#.#.#
+            #######";
+        let (graph, start, end) = parse_input(input).unwrap();
+        let near = Point::new(5, 3);
+        let walled_in = Point::new(3, 3);
+
+        assert_eq!(graph.shortest_cost(near, end).unwrap(), 1002);
+        assert_eq!(
+            graph
+                .best_from_starts(&[near, walled_in, start], end)
+                .unwrap(),
+            (start, 4)
+        );
+        assert_eq!(graph.best_from_starts(&[near], end).unwrap(), (near, 1002));
+        assert!(matches!(
+            graph.best_from_starts(&[walled_in], end),
+            Err(GraphError::NoPathFound)
+        ));
+        assert!(matches!(
+            graph.best_from_starts(&[], end),
+            Err(GraphError::NoPathFound)
+        ));
+    }
+
+    #[test]
+    fn test_graph_from_str() {
+        let graph: Graph = INPUT_ONE.parse().unwrap();
+
+        assert_eq!(graph.start(), Point::new(1, 13));
+        assert_eq!(graph.end(), Point::new(13, 1));
+        assert_eq!(graph, parse_input(INPUT_ONE).unwrap().0);
+
+        let solution = graph.astar_all_paths(graph.start(), graph.end()).unwrap();
+        assert_eq!(solution.cost(), 7036);
+    }
+
+    #[test]
+    fn test_weighted_tiles() {
+        // Going through the mud is 8 moves shorter, but costs 16 extra
+        let input = r"
+            #########
+            #S......#
+            ###9###.#
+            ###9###.#
+            #E......#
+            #########
+        ";
+        let (graph, start, end) = parse_input(input).unwrap();
+        let solution = graph.astar_all_paths(start, end).unwrap();
+
+        assert_eq!(solution.cost(), 2015);
+        assert_eq!(graph.shortest_cost(start, end).unwrap(), 2015);
+        assert_eq!(solution.paths().len(), 1);
+        let (tiles, _) = graph.best_path_tiles(start, end).unwrap();
+        assert_eq!(tiles, solution.unique_tiles());
+        assert!(!tiles.contains(&Point::new(3, 2)));
+
+        // Without the mud, the short way wins
+        let (graph, start, end) = parse_input(&input.replace('9', ".")).unwrap();
+        assert_eq!(graph.shortest_cost(start, end).unwrap(), 2007);
+    }
+
+    #[test]
+    fn test_invalid_weights() {
+        let start = Point::new(0, 0);
+        let end = Point::new(1, 0);
+
+        for weight in [-1, 0, 10] {
+            let pos_map = HashMap::from([(start, 1), (end, weight)]);
+            let result = Graph::new(pos_map, 2, 1, start, end);
+            assert!(
+                matches!(result, Err(GraphError::InvalidWeight(p, w)) if p == end && w == weight)
+            );
+        }
+
+        let pos_map = HashMap::from([(start, 1), (end, 9)]);
+        let graph = Graph::new(pos_map, 2, 1, start, end).unwrap();
+        assert_eq!(graph.shortest_cost(start, end).unwrap(), 9);
+    }
+
+    #[test]
+    fn test_weighted_tiles_on_best_path() {
+        // The only way to the end is through the mud
+        let input = r"
+            #####
+            #S9E#
+            #####
+        ";
+        let (graph, start, end) = parse_input(input).unwrap();
+        let solution = graph.astar_all_paths(start, end).unwrap();
+
+        assert_eq!(solution.cost(), 10);
+        assert_eq!(solution.paths().len(), 1);
+        assert_eq!(
+            solution.unique_tiles(),
+            HashSet::from([start, Point::new(2, 1), end])
+        );
+        assert_eq!(
+            graph.best_path_tiles(start, end).unwrap(),
+            (solution.unique_tiles(), 10)
+        );
+
+        // The top row is a dead end, so the best path turns twice before crossing the mud
+        let input = r"
+            #######
+            #S.9..#
+            #.#####
+            #...9E#
+            #######
+        ";
+        let (graph, start, end) = parse_input(input).unwrap();
+        let solution = graph.astar_all_paths(start, end).unwrap();
+
+        assert_eq!(solution.cost(), 2014);
+        assert_eq!(graph.shortest_cost(start, end).unwrap(), 2014);
+        assert_eq!(solution.paths().len(), 1);
+        assert_eq!(solution.unique_tiles().len(), 7);
+        assert!(solution.unique_tiles().contains(&Point::new(4, 3)));
+        assert_eq!(
+            graph.best_path_tiles(start, end).unwrap(),
+            (solution.unique_tiles(), 2014)
+        );
+    }
 }
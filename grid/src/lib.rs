@@ -0,0 +1,186 @@
+//! Shared 2d grid helpers, so each day doesn't have to redefine its own point type.
+
+use std::{
+    fmt::{Debug, Display},
+    hash::Hash,
+    ops::{Add, Sub},
+};
+
+/// Integer types which can be used as the components of a [`Point`].
+pub trait Scalar:
+    Copy + Eq + Ord + Hash + Debug + Display + Add<Output = Self> + Sub<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+}
+
+macro_rules! impl_scalar {
+    ($($t:ty),*) => {
+        $(
+            impl Scalar for $t {
+                const ZERO: Self = 0;
+                const ONE: Self = 1;
+
+                fn checked_add(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_add(self, rhs)
+                }
+
+                fn checked_sub(self, rhs: Self) -> Option<Self> {
+                    <$t>::checked_sub(self, rhs)
+                }
+            }
+        )*
+    };
+}
+
+impl_scalar!(i32, i64, isize, u32, u64, usize);
+
+/// 2d point on a grid, where y grows downwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Point<T> {
+    pub x: T,
+    pub y: T,
+}
+
+impl<T: Scalar> Point<T> {
+    pub fn new(x: T, y: T) -> Self {
+        Point { x, y }
+    }
+
+    /// Checks if the point lies within a grid of the given size, with the origin in the top left.
+    pub fn in_bounds(&self, width: T, height: T) -> bool {
+        self.x >= T::ZERO && self.x < width && self.y >= T::ZERO && self.y < height
+    }
+
+    /// The four orthogonal neighbours, in the order North, South, West, East.
+    /// A neighbour is None if it can't be represented by `T`, e.g. below zero for unsigned types.
+    pub fn neighbours(&self) -> [Option<Point<T>>; 4] {
+        [
+            self.y.checked_sub(T::ONE).map(|y| Point::new(self.x, y)), // North
+            self.y.checked_add(T::ONE).map(|y| Point::new(self.x, y)), // South
+            self.x.checked_sub(T::ONE).map(|x| Point::new(x, self.y)), // West
+            self.x.checked_add(T::ONE).map(|x| Point::new(x, self.y)), // East
+        ]
+    }
+
+    /// The orthogonal neighbours which lie within a grid of the given size.
+    pub fn neighbours_bounded(&self, width: T, height: T) -> Vec<Point<T>> {
+        self.neighbours()
+            .into_iter()
+            .flatten()
+            .filter(|n| n.in_bounds(width, height))
+            .collect()
+    }
+
+    /// All eight neighbours, including ordinals, ordered row by row from the North West.
+    /// As with `neighbours`, any that can't be represented by `T` are None.
+    pub fn all_neighbours(&self) -> [Option<Point<T>>; 8] {
+        let (up, down) = (self.y.checked_sub(T::ONE), self.y.checked_add(T::ONE));
+        let (left, right) = (self.x.checked_sub(T::ONE), self.x.checked_add(T::ONE));
+        let point = |x: Option<T>, y: Option<T>| Some(Point::new(x?, y?));
+
+        [
+            point(left, up),            // NW
+            point(Some(self.x), up),    // N
+            point(right, up),           // NE
+            point(left, Some(self.y)),  // W
+            point(right, Some(self.y)), // E
+            point(left, down),          // SW
+            point(Some(self.x), down),  // S
+            point(right, down),         // SE
+        ]
+    }
+
+    /// All eight neighbours which lie within a grid of the given size, in the same order as `all_neighbours`.
+    pub fn neighbours8(&self, width: T, height: T) -> Vec<Point<T>> {
+        self.all_neighbours()
+            .into_iter()
+            .flatten()
+            .filter(|n| n.in_bounds(width, height))
+            .collect()
+    }
+
+    /// Sum of the absolute differences along each axis.
+    pub fn manhattan_distance(&self, other: &Point<T>) -> T {
+        let abs_diff = |a: T, b: T| if a > b { a - b } else { b - a };
+        abs_diff(self.x, other.x) + abs_diff(self.y, other.y)
+    }
+}
+
+impl<T: Display> Display for Point<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_in_bounds() {
+        assert!(Point::new(0, 0).in_bounds(3, 3));
+        assert!(Point::new(2, 2).in_bounds(3, 3));
+        assert!(!Point::new(3, 2).in_bounds(3, 3));
+        assert!(!Point::new(-1, 0).in_bounds(3, 3));
+    }
+
+    #[test]
+    fn test_neighbours_unsigned() {
+        let expected = [
+            None,
+            Some(Point::new(0usize, 1)),
+            None,
+            Some(Point::new(1, 0)),
+        ];
+
+        assert_eq!(Point::new(0usize, 0).neighbours(), expected);
+    }
+
+    #[test]
+    fn test_neighbours_bounded() {
+        let neighbours = Point::new(0i32, 1).neighbours_bounded(2, 2);
+
+        assert_eq!(neighbours, vec![Point::new(0, 0), Point::new(1, 1)]);
+    }
+
+    #[test]
+    fn test_all_neighbours() {
+        let neighbours = Point::new(0i32, 0).all_neighbours();
+
+        assert_eq!(neighbours[0], Some(Point::new(-1, -1)));
+        assert_eq!(neighbours[7], Some(Point::new(1, 1)));
+        assert_eq!(Point::new(0usize, 0).all_neighbours()[0], None);
+    }
+
+    #[test]
+    fn test_neighbours8() {
+        let corner = Point::new(0usize, 0).neighbours8(3, 3);
+        assert_eq!(
+            corner,
+            vec![Point::new(1, 0), Point::new(0, 1), Point::new(1, 1)]
+        );
+        assert_eq!(Point::new(1i32, 1).neighbours8(3, 3).len(), 8);
+        assert_eq!(Point::new(2i32, 1).neighbours8(3, 3).len(), 5);
+    }
+
+    #[test]
+    fn test_manhattan_distance() {
+        assert_eq!(
+            Point::new(1usize, 5).manhattan_distance(&Point::new(4, 2)),
+            6
+        );
+        assert_eq!(
+            Point::new(-2i64, 3).manhattan_distance(&Point::new(2, -1)),
+            8
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Point::new(3, 4).to_string(), "(3, 4)");
+    }
+}